@@ -0,0 +1,36 @@
+//times the tokenizer on growing inputs, the time per line should stay flat if lexing is linear
+use std::time::{Duration, Instant};
+
+use bs_lang::Tokenizer;
+
+const LINE: &str = "let x: float = 10 + 20.1; let s: string = \"héllo wörld\"; // comment\n";
+
+fn time(input: &str) -> Duration {
+    //take the best of a few runs to cut down on noise
+    (0..5)
+        .map(|_| {
+            let tokenizer = Tokenizer::new(input);
+            let start = Instant::now();
+
+            assert!(tokenizer.count() > 0);
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn main() {
+    println!("{:>8} {:>12} {:>14}", "lines", "total", "per line");
+
+    for lines in [1_000, 2_000, 4_000, 8_000, 16_000] {
+        let input = LINE.repeat(lines);
+        let elapsed = time(&input);
+
+        println!(
+            "{:>8} {:>12.2?} {:>14.2?}",
+            lines,
+            elapsed,
+            elapsed / lines as u32
+        );
+    }
+}
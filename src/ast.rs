@@ -0,0 +1,380 @@
+use crate::{Position, Type};
+
+//the root of a parsed file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    pub statements: Vec<Stmt>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stmt {
+    Let {
+        name: String,
+        name_position: Position,
+        ty: Option<Type>,
+        init: Expr,
+        //the /// comment lines written right above the declaration
+        doc: Option<String>,
+        position: Position,
+    },
+    Const {
+        name: String,
+        name_position: Position,
+        ty: Option<Type>,
+        init: Expr,
+        doc: Option<String>,
+        position: Position,
+    },
+    Func {
+        name: String,
+        name_position: Position,
+        params: Vec<Param>,
+        return_ty: Option<Type>,
+        body: Block,
+        doc: Option<String>,
+        position: Position,
+    },
+    //struct Name { field: type, ... }
+    Struct {
+        name: String,
+        name_position: Position,
+        fields: Vec<Field>,
+        doc: Option<String>,
+        position: Position,
+    },
+    //enum Name { Variant, Variant(type, ...), ... }
+    Enum {
+        name: String,
+        name_position: Position,
+        variants: Vec<Variant>,
+        doc: Option<String>,
+        position: Position,
+    },
+    //impl Name { func method(self, ...) { ... } }, every method is a Stmt::Func whose first
+    //parameter is self, typed as the struct it belongs to
+    Impl {
+        name: String,
+        name_position: Position,
+        methods: Vec<Stmt>,
+        position: Position,
+    },
+    Return {
+        value: Option<Expr>,
+        position: Position,
+    },
+    //an expression evaluated for its side effects, like a call
+    Expr {
+        expr: Expr,
+        position: Position,
+    },
+    Block(Block),
+    //else_branch is either another if (for else if) or a block
+    If {
+        condition: Expr,
+        then_branch: Block,
+        else_branch: Option<Box<Stmt>>,
+        position: Position,
+    },
+    While {
+        condition: Expr,
+        body: Block,
+        position: Position,
+    },
+    //for (init; condition; update) { ... }, every part may be left out
+    For {
+        init: Option<Box<Stmt>>,
+        condition: Option<Expr>,
+        update: Option<Expr>,
+        body: Block,
+        position: Position,
+    },
+    //for name in iterable { ... }
+    ForIn {
+        name: String,
+        name_position: Position,
+        iterable: Expr,
+        body: Block,
+        position: Position,
+    },
+    Break {
+        position: Position,
+    },
+    Continue {
+        position: Position,
+    },
+    //a statement that failed to parse, kept so the rest of the file can still be checked
+    Error {
+        position: Position,
+    },
+}
+
+impl Stmt {
+    pub fn position(&self) -> &Position {
+        match self {
+            Stmt::Let { position, .. }
+            | Stmt::Const { position, .. }
+            | Stmt::Func { position, .. }
+            | Stmt::Struct { position, .. }
+            | Stmt::Enum { position, .. }
+            | Stmt::Impl { position, .. }
+            | Stmt::Return { position, .. }
+            | Stmt::Expr { position, .. }
+            | Stmt::If { position, .. }
+            | Stmt::While { position, .. }
+            | Stmt::For { position, .. }
+            | Stmt::ForIn { position, .. }
+            | Stmt::Break { position }
+            | Stmt::Continue { position }
+            | Stmt::Error { position } => position,
+            Stmt::Block(block) => &block.position,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param {
+    pub name: String,
+    pub ty: Type,
+    pub position: Position,
+}
+
+//a field in a struct declaration
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub name: String,
+    pub ty: Type,
+    pub position: Position,
+}
+
+//a variant in an enum declaration with the types of its payload, empty for a plain variant
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variant {
+    pub name: String,
+    pub fields: Vec<Type>,
+    pub position: Position,
+}
+
+//a field: value pair in a struct literal
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldInit {
+    pub name: String,
+    pub value: Expr,
+    pub position: Position,
+}
+
+//a list of statements between { and }
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    pub statements: Vec<Stmt>,
+    pub position: Position,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub position: Position,
+}
+
+impl Expr {
+    pub fn new(kind: ExprKind, position: Position) -> Self {
+        Expr { kind, position }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprKind {
+    Int(i64),
+    Float(f64),
+    String(String),
+    Bool(bool),
+    Array(Vec<Expr>),
+    //"a ${x} b" as its text and expression parts in order, empty text parts are left out
+    Interpolation(Vec<Expr>),
+    Identifier(String),
+    Unary {
+        op: UnaryOp,
+        operand: Box<Expr>,
+    },
+    Postfix {
+        op: PostfixOp,
+        operand: Box<Expr>,
+    },
+    Binary {
+        op: BinaryOp,
+        left: Box<Expr>,
+        right: Box<Expr>,
+    },
+    Call {
+        callee: Box<Expr>,
+        args: Vec<Expr>,
+    },
+    Index {
+        target: Box<Expr>,
+        index: Box<Expr>,
+    },
+    //Name { field: value, ... }
+    StructLiteral {
+        name: String,
+        fields: Vec<FieldInit>,
+    },
+    //Enum::Variant or Enum::Variant(value, ...)
+    Variant {
+        enum_name: String,
+        variant: String,
+        args: Vec<Expr>,
+    },
+    //match subject { pattern if guard => value, ... }
+    Match {
+        subject: Box<Expr>,
+        arms: Vec<MatchArm>,
+    },
+    //target.name, calling it as target.name() calls a method
+    Field {
+        target: Box<Expr>,
+        name: String,
+    },
+    Assign {
+        target: Box<Expr>,
+        value: Box<Expr>,
+    },
+    //an expression that failed to parse, the error for it has already been reported
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub guard: Option<Expr>,
+    pub body: Expr,
+    pub position: Position,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pattern {
+    pub kind: PatternKind,
+    pub position: Position,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatternKind {
+    //_ matches anything and binds nothing
+    Wildcard,
+    //an int, float, string or bool literal, compared with ==
+    Literal(Expr),
+    //a name matches anything and binds the value to it
+    Binding(String),
+    //Enum::Variant(pattern, ...) matches that variant when the payload matches too
+    Variant {
+        enum_name: String,
+        variant: String,
+        fields: Vec<Pattern>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnaryOp {
+    Neg,
+    Not,
+    Increment,
+    Decrement,
+}
+
+impl UnaryOp {
+    pub fn from_operator(value: &str) -> Option<Self> {
+        match value {
+            "-" => Some(UnaryOp::Neg),
+            "!" => Some(UnaryOp::Not),
+            "++" => Some(UnaryOp::Increment),
+            "--" => Some(UnaryOp::Decrement),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UnaryOp::Neg => "-",
+            UnaryOp::Not => "!",
+            UnaryOp::Increment => "++",
+            UnaryOp::Decrement => "--",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PostfixOp {
+    Increment,
+    Decrement,
+}
+
+impl PostfixOp {
+    pub fn from_operator(value: &str) -> Option<Self> {
+        match value {
+            "++" => Some(PostfixOp::Increment),
+            "--" => Some(PostfixOp::Decrement),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PostfixOp::Increment => "++",
+            PostfixOp::Decrement => "--",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Eq,
+    NotEq,
+    Less,
+    Greater,
+    LessEq,
+    GreaterEq,
+    And,
+    Or,
+}
+
+impl BinaryOp {
+    pub fn from_operator(value: &str) -> Option<Self> {
+        match value {
+            "+" => Some(BinaryOp::Add),
+            "-" => Some(BinaryOp::Sub),
+            "*" => Some(BinaryOp::Mul),
+            "/" => Some(BinaryOp::Div),
+            "%" => Some(BinaryOp::Rem),
+            "==" => Some(BinaryOp::Eq),
+            "!=" => Some(BinaryOp::NotEq),
+            "<" => Some(BinaryOp::Less),
+            ">" => Some(BinaryOp::Greater),
+            "<=" => Some(BinaryOp::LessEq),
+            ">=" => Some(BinaryOp::GreaterEq),
+            "&&" => Some(BinaryOp::And),
+            "||" => Some(BinaryOp::Or),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Rem => "%",
+            BinaryOp::Eq => "==",
+            BinaryOp::NotEq => "!=",
+            BinaryOp::Less => "<",
+            BinaryOp::Greater => ">",
+            BinaryOp::LessEq => "<=",
+            BinaryOp::GreaterEq => ">=",
+            BinaryOp::And => "&&",
+            BinaryOp::Or => "||",
+        }
+    }
+}
@@ -0,0 +1,419 @@
+use std::fmt;
+use std::iter::Peekable;
+
+use crate::ast::{Block, Expr, ExprKind, MatchArm, Pattern, PatternKind, Stmt};
+use crate::{FileId, ParseError, Parser, Position, Span, Token, TokenKind, Tokenizer};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //the tree as an s-expression of node kinds and token texts, trivia left out
+    fn shape(node: &SyntaxNode) -> String {
+        let children = node
+            .children
+            .iter()
+            .map(|child| match child {
+                SyntaxElement::Node(node) => shape(node),
+                SyntaxElement::Token(token) if token.token.kind == TokenKind::Eof => {
+                    "<eof>".to_string()
+                }
+                SyntaxElement::Token(token) => token.text.clone(),
+            })
+            .collect::<Vec<String>>();
+
+        format!("({:?} {})", node.kind, children.join(" "))
+    }
+
+    #[test]
+    fn round_trips_the_source() {
+        for source in [
+            "",
+            "  \n// only a comment\n",
+            "let x: int = 1 +   2 ; // sum\n\n/* block\n comment */ print( x );\n",
+            "/// adds\nfunc add(a: int, b: int): int {\n\treturn a + b;\r\n}\n",
+            "struct P { x: int, y: int }\nimpl P { func len(self): int { return self.x; } }\n",
+            "enum E { A(int), B }\nlet v = match E::A(1) { E::A(n) if n > 0 => n, _ => 0 };",
+            "for (let i = 0; i < 3; i++) { for x in [1, 2] { print(\"${x} ${i}\"); } }",
+            "if a { b = 1; } else if c { } else { d[0] = -e.f; }",
+            "let = ; func ( { } } let s = \"open",
+            "x = 1 /* unterminated",
+        ] {
+            let (tree, _) = parse_cst(source);
+
+            assert_eq!(tree.to_string(), source);
+            assert_eq!(tree.span, Span::new(FileId(0), 0, source.len()));
+        }
+    }
+
+    #[test]
+    fn nodes_follow_the_ast() {
+        let (tree, errors) = parse_cst("let x = (1 + 2) * y; // done\nprint(x);");
+
+        assert!(errors.is_empty());
+        assert_eq!(
+            shape(&tree),
+            "(Program (Let let x = (Binary (Binary ( (Literal 1) + (Literal 2) )) * (Identifier y)) ;) \
+             (ExprStmt (Call (Identifier print) ( (Identifier x) )) ;) <eof>)"
+        );
+
+        let SyntaxElement::Node(statement) = &tree.children[0] else {
+            panic!("expected a node");
+        };
+        let semicolon = statement.tokens().last().copied().unwrap();
+
+        assert_eq!(semicolon.text, ";");
+        assert_eq!(semicolon.token.trailing_trivia[1].text, "// done");
+    }
+
+    #[test]
+    fn errors_stay_in_the_tree() {
+        let (tree, errors) = parse_cst("let x = ;\nlet = 2;\nlet y = 3;");
+
+        assert_eq!(errors.len(), 2);
+        assert_eq!(
+            shape(&tree),
+            "(Program (Let let x = (Error ) ;) (Error let = 2 ;) (Let let y = (Literal 3) ;) <eof>)"
+        );
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NodeKind {
+    Program,
+    Let,
+    Const,
+    Func,
+    Param,
+    Struct,
+    Field,
+    Enum,
+    Variant,
+    Impl,
+    Return,
+    ExprStmt,
+    Block,
+    If,
+    While,
+    For,
+    ForIn,
+    Break,
+    Continue,
+    Literal,
+    Array,
+    Interpolation,
+    Identifier,
+    Unary,
+    Postfix,
+    Binary,
+    Call,
+    Index,
+    StructLiteral,
+    FieldInit,
+    VariantValue,
+    Match,
+    MatchArm,
+    Pattern,
+    FieldAccess,
+    Assign,
+    //a statement or expression that failed to parse, its tokens are still kept
+    Error,
+}
+
+//a token with the text it was read from, which the token kind alone can not give back
+//for strings with escapes or numbers like 0x1F
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxToken {
+    pub token: Token,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+//a node of the concrete syntax tree, its children hold every token in its span in source
+//order so printing the tree gives back the input byte for byte
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxNode {
+    pub kind: NodeKind,
+    pub span: Span,
+    pub children: Vec<SyntaxElement>,
+}
+
+impl SyntaxNode {
+    //every token under this node in source order
+    pub fn tokens(&self) -> Vec<&SyntaxToken> {
+        let mut tokens = Vec::new();
+
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(node) => tokens.extend(node.tokens()),
+                SyntaxElement::Token(token) => tokens.push(token),
+            }
+        }
+
+        tokens
+    }
+}
+
+impl fmt::Display for SyntaxToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for trivia in &self.token.leading_trivia {
+            write!(f, "{}", trivia.text)?;
+        }
+
+        write!(f, "{}", self.text)?;
+
+        for trivia in &self.token.trailing_trivia {
+            write!(f, "{}", trivia.text)?;
+        }
+
+        Ok(())
+    }
+}
+
+//the source text of the node with all of its trivia
+impl fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(node) => write!(f, "{}", node)?,
+                SyntaxElement::Token(token) => write!(f, "{}", token)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+//parse source into a concrete syntax tree, which is built even when there are syntax errors
+//so tools can work on files that are being edited
+pub fn parse_cst(source: &str) -> (SyntaxNode, Vec<ParseError>) {
+    let tokens = Tokenizer::new(source).lossless().collect::<Vec<Token>>();
+    let (program, errors) = Parser::from_tokens(tokens.clone()).parse_recovering();
+
+    let mut tokens = tokens
+        .into_iter()
+        .map(|token| SyntaxToken {
+            text: source[token.position.span.range()].to_string(),
+            token,
+        })
+        .peekable();
+
+    let outline = Outline {
+        kind: NodeKind::Program,
+        span: Span::new(FileId::default(), 0, source.len()),
+        children: program.statements.iter().map(statement).collect(),
+    };
+
+    (outline.build(&mut tokens), errors)
+}
+
+//the nodes of the tree and their spans, taken from the ast before the tokens are put in
+struct Outline {
+    kind: NodeKind,
+    span: Span,
+    children: Vec<Outline>,
+}
+
+impl Outline {
+    fn new(kind: NodeKind, position: &Position, children: Vec<Outline>) -> Self {
+        Outline {
+            kind,
+            span: position.span,
+            children,
+        }
+    }
+
+    //a token goes into the innermost node whose span holds it, tokens are always taken in
+    //order so none are lost or moved even if the ast leaves some out of every child
+    fn build(self, tokens: &mut Peekable<impl Iterator<Item = SyntaxToken>>) -> SyntaxNode {
+        let mut children = Vec::new();
+
+        for child in self.children {
+            while let Some(token) =
+                tokens.next_if(|token| token.token.position.span.start_byte < child.span.start_byte)
+            {
+                children.push(SyntaxElement::Token(token));
+            }
+
+            children.push(SyntaxElement::Node(child.build(tokens)));
+        }
+
+        //the program takes everything that is left, which is at least the eof token
+        while let Some(token) = tokens.next_if(|token| {
+            self.kind == NodeKind::Program
+                || token.token.kind != TokenKind::Eof
+                    && token.token.position.span.end_byte <= self.span.end_byte
+        }) {
+            children.push(SyntaxElement::Token(token));
+        }
+
+        SyntaxNode {
+            kind: self.kind,
+            span: self.span,
+            children,
+        }
+    }
+}
+
+fn statement(stmt: &Stmt) -> Outline {
+    let (kind, children) = match stmt {
+        Stmt::Let { init, .. } => (NodeKind::Let, vec![expression(init)]),
+        Stmt::Const { init, .. } => (NodeKind::Const, vec![expression(init)]),
+        Stmt::Func { params, body, .. } => {
+            let mut children = params
+                .iter()
+                .map(|param| Outline::new(NodeKind::Param, &param.position, Vec::new()))
+                .collect::<Vec<Outline>>();
+            children.push(block(body));
+
+            (NodeKind::Func, children)
+        }
+        Stmt::Struct { fields, .. } => (
+            NodeKind::Struct,
+            fields
+                .iter()
+                .map(|field| Outline::new(NodeKind::Field, &field.position, Vec::new()))
+                .collect(),
+        ),
+        Stmt::Enum { variants, .. } => (
+            NodeKind::Enum,
+            variants
+                .iter()
+                .map(|variant| Outline::new(NodeKind::Variant, &variant.position, Vec::new()))
+                .collect(),
+        ),
+        Stmt::Impl { methods, .. } => (NodeKind::Impl, methods.iter().map(statement).collect()),
+        Stmt::Return { value, .. } => (NodeKind::Return, value.iter().map(expression).collect()),
+        Stmt::Expr { expr, .. } => (NodeKind::ExprStmt, vec![expression(expr)]),
+        Stmt::Block(body) => return block(body),
+        Stmt::If {
+            condition,
+            then_branch,
+            else_branch,
+            ..
+        } => {
+            let mut children = vec![expression(condition), block(then_branch)];
+            children.extend(else_branch.iter().map(|stmt| statement(stmt)));
+
+            (NodeKind::If, children)
+        }
+        Stmt::While {
+            condition, body, ..
+        } => (NodeKind::While, vec![expression(condition), block(body)]),
+        Stmt::For {
+            init,
+            condition,
+            update,
+            body,
+            ..
+        } => {
+            let mut children = init
+                .iter()
+                .map(|stmt| statement(stmt))
+                .collect::<Vec<Outline>>();
+            children.extend(condition.iter().chain(update).map(expression));
+            children.push(block(body));
+
+            (NodeKind::For, children)
+        }
+        Stmt::ForIn { iterable, body, .. } => {
+            (NodeKind::ForIn, vec![expression(iterable), block(body)])
+        }
+        Stmt::Break { .. } => (NodeKind::Break, Vec::new()),
+        Stmt::Continue { .. } => (NodeKind::Continue, Vec::new()),
+        Stmt::Error { .. } => (NodeKind::Error, Vec::new()),
+    };
+
+    Outline::new(kind, stmt.position(), children)
+}
+
+fn block(block: &Block) -> Outline {
+    Outline::new(
+        NodeKind::Block,
+        &block.position,
+        block.statements.iter().map(statement).collect(),
+    )
+}
+
+fn expression(expr: &Expr) -> Outline {
+    let (kind, children) = match &expr.kind {
+        ExprKind::Int(_) | ExprKind::Float(_) | ExprKind::String(_) | ExprKind::Bool(_) => {
+            (NodeKind::Literal, Vec::new())
+        }
+        ExprKind::Array(elements) => (NodeKind::Array, elements.iter().map(expression).collect()),
+        ExprKind::Interpolation(parts) => (
+            NodeKind::Interpolation,
+            parts.iter().map(expression).collect(),
+        ),
+        ExprKind::Identifier(_) => (NodeKind::Identifier, Vec::new()),
+        ExprKind::Unary { operand, .. } => (NodeKind::Unary, vec![expression(operand)]),
+        ExprKind::Postfix { operand, .. } => (NodeKind::Postfix, vec![expression(operand)]),
+        ExprKind::Binary { left, right, .. } => {
+            (NodeKind::Binary, vec![expression(left), expression(right)])
+        }
+        ExprKind::Call { callee, args } => (
+            NodeKind::Call,
+            std::iter::once(callee.as_ref())
+                .chain(args)
+                .map(expression)
+                .collect(),
+        ),
+        ExprKind::Index { target, index } => {
+            (NodeKind::Index, vec![expression(target), expression(index)])
+        }
+        ExprKind::StructLiteral { fields, .. } => (
+            NodeKind::StructLiteral,
+            fields
+                .iter()
+                .map(|field| {
+                    Outline::new(
+                        NodeKind::FieldInit,
+                        &field.position,
+                        vec![expression(&field.value)],
+                    )
+                })
+                .collect(),
+        ),
+        ExprKind::Variant { args, .. } => (
+            NodeKind::VariantValue,
+            args.iter().map(expression).collect(),
+        ),
+        ExprKind::Match { subject, arms } => {
+            let mut children = vec![expression(subject)];
+            children.extend(arms.iter().map(arm));
+
+            (NodeKind::Match, children)
+        }
+        ExprKind::Field { target, .. } => (NodeKind::FieldAccess, vec![expression(target)]),
+        ExprKind::Assign { target, value } => (
+            NodeKind::Assign,
+            vec![expression(target), expression(value)],
+        ),
+        ExprKind::Error => (NodeKind::Error, Vec::new()),
+    };
+
+    Outline::new(kind, &expr.position, children)
+}
+
+fn arm(arm: &MatchArm) -> Outline {
+    let mut children = vec![pattern(&arm.pattern)];
+    children.extend(arm.guard.iter().map(expression));
+    children.push(expression(&arm.body));
+
+    Outline::new(NodeKind::MatchArm, &arm.position, children)
+}
+
+fn pattern(pattern: &Pattern) -> Outline {
+    let children = match &pattern.kind {
+        PatternKind::Wildcard | PatternKind::Binding(_) => Vec::new(),
+        PatternKind::Literal(literal) => vec![expression(literal)],
+        PatternKind::Variant { fields, .. } => fields.iter().map(self::pattern).collect(),
+    };
+
+    Outline::new(NodeKind::Pattern, &pattern.position, children)
+}
@@ -0,0 +1,383 @@
+use std::fmt;
+
+use crate::{Error, ParseError, Position, RuntimeError, Token, TokenKind, TypeError};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{check, parse, ErrorType, Interpreter, Tokenizer};
+
+    #[test]
+    fn type_error() {
+        let source = "let a = 1;\nlet x: int = \"a\";";
+        let errors = check(&parse(source).unwrap());
+
+        assert_eq!(
+            Diagnostic::from(&errors[0]).render("main.bs", source),
+            "\
+error[E0006]: Expected int, found string
+ --> main.bs:2:1
+  |
+2 | let x: int = \"a\";
+  | ^^^^^^^^^^^^^^^^^ x
+"
+        );
+    }
+
+    #[test]
+    fn parse_error() {
+        let source = "let x = 1\nprint(x);";
+        let error = parse(source).unwrap_err();
+
+        assert_eq!(
+            Diagnostic::from(&error).render("main.bs", source),
+            "\
+error[E0001]: Expected `;`, found identifier `print`
+ --> main.bs:2:1
+  |
+2 | print(x);
+  | ^^^^^ identifier `print`
+"
+        );
+    }
+
+    #[test]
+    fn lexer_error() {
+        let source = "let s = \"abc";
+        let token = Tokenizer::new(source)
+            .find(|token| matches!(token.kind, TokenKind::Error(_)))
+            .unwrap();
+
+        let diagnostic = Diagnostic::from_token(&token).unwrap();
+
+        assert_eq!(diagnostic.code.as_deref(), Some("E0012"));
+        assert!(diagnostic
+            .render("main.bs", source)
+            .contains("1 | let s = \"abc\n  |         ^^^^"));
+        assert_eq!(
+            Diagnostic::from_token(&Token::new(TokenKind::Eof, 1, 1, 1)),
+            None
+        );
+    }
+
+    #[test]
+    fn invalid_escape() {
+        let source = "let s = \"a\\qb\";";
+        let error = parse(source).unwrap_err();
+
+        assert_eq!(
+            Diagnostic::from(&error).render("main.bs", source),
+            "\
+error[E0014]: Invalid escape sequence
+ --> main.bs:1:11
+  |
+1 | let s = \"a\\qb\";
+  |         ------
+  |           ^^ \\q
+"
+        );
+    }
+
+    #[test]
+    fn runtime_error() {
+        let source = "let x = 0;\nprint(1 / x);";
+        let error = Interpreter::with_output(std::io::sink())
+            .run(source)
+            .unwrap_err();
+
+        assert_eq!(
+            Diagnostic::from(&error).render("main.bs", source),
+            "\
+error[E0011]: Division by zero
+ --> main.bs:2:7
+  |
+2 | print(1 / x);
+  |       ^^^^^ 1 / 0
+"
+        );
+    }
+
+    #[test]
+    fn labels_notes_and_help() {
+        let source = "const x = 1;\nlet y = 2;\n\nlet z = 3;\n\tx = y;";
+        let diagnostic = Diagnostic::error("Cannot assign to a constant")
+            .with_code(ErrorType::InvalidAssignment.code())
+            .with_label(Position::new(5, 2, 3), "assigned here")
+            .with_secondary_label(Position::new(1, 7, 8), "declared as a constant here")
+            .with_secondary_label(Position::new(5, 6, 7), "")
+            .with_note("constants can only be set once")
+            .with_help("declare it with `let` instead");
+
+        assert_eq!(
+            diagnostic.render("main.bs", source),
+            "\
+error[E0007]: Cannot assign to a constant
+ --> main.bs:5:2
+  |
+1 | const x = 1;
+  |       - declared as a constant here
+...
+5 | \tx = y;
+  | \t^ assigned here
+  | \t    -
+  |
+  = note: constants can only be set once
+  = help: declare it with `let` instead
+"
+        );
+    }
+
+    #[test]
+    fn end_of_input_and_wide_gutters() {
+        let source = format!("{}let x = (1", "\n".repeat(9));
+        let error = parse(&source).unwrap_err();
+
+        assert_eq!(
+            Diagnostic::from(&error).render("main.bs", &source),
+            "\
+error[E0003]: Expected `)`, found end of input
+  --> main.bs:10:11
+   |
+10 | let x = (1
+   |           ^ end of input
+"
+        );
+    }
+
+    #[test]
+    fn without_labels() {
+        let diagnostic =
+            Diagnostic::new(Severity::Warning, "Nothing to run").with_note("the file is empty");
+
+        assert_eq!(diagnostic.to_string(), "warning: Nothing to run");
+        assert_eq!(
+            diagnostic.render("main.bs", ""),
+            "warning: Nothing to run\n = note: the file is empty\n"
+        );
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Note => write!(f, "note"),
+        }
+    }
+}
+
+//a span of source with a short message, primary labels point at the cause and are
+//underlined with ^, secondary ones add context and are underlined with -
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub position: Position,
+    pub message: String,
+    pub primary: bool,
+}
+
+//a message about the source for the user, with everything needed to render it like rustc does
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: Option<String>,
+    pub message: String,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+    pub help: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, message: &str) -> Self {
+        Diagnostic {
+            severity,
+            code: None,
+            message: message.to_string(),
+            labels: Vec::new(),
+            notes: Vec::new(),
+            help: None,
+        }
+    }
+
+    pub fn error(message: &str) -> Self {
+        Diagnostic::new(Severity::Error, message)
+    }
+
+    pub fn warning(message: &str) -> Self {
+        Diagnostic::new(Severity::Warning, message)
+    }
+
+    //an error from any stage, the offending text becomes the label of its position; an error
+    //that points inside position is labeled there and position is kept as context
+    pub fn from_error(error: &Error, position: &Position) -> Self {
+        let diagnostic = Diagnostic::error(error.message()).with_code(error.error_type().code());
+
+        match error.position() {
+            Some(inner) => diagnostic
+                .with_label(inner.clone(), error.errorstring())
+                .with_secondary_label(position.clone(), ""),
+            None => diagnostic.with_label(position.clone(), error.errorstring()),
+        }
+    }
+
+    //the diagnostic for an error token from the lexer, None for any other token
+    pub fn from_token(token: &Token) -> Option<Self> {
+        match &token.kind {
+            TokenKind::Error(error) => Some(Diagnostic::from_error(error, &token.position)),
+            _ => None,
+        }
+    }
+
+    pub fn with_code(mut self, code: &str) -> Self {
+        self.code = Some(code.to_string());
+        self
+    }
+
+    pub fn with_label(mut self, position: Position, message: &str) -> Self {
+        self.labels.push(Label {
+            position,
+            message: message.to_string(),
+            primary: true,
+        });
+        self
+    }
+
+    pub fn with_secondary_label(mut self, position: Position, message: &str) -> Self {
+        self.labels.push(Label {
+            position,
+            message: message.to_string(),
+            primary: false,
+        });
+        self
+    }
+
+    pub fn with_note(mut self, note: &str) -> Self {
+        self.notes.push(note.to_string());
+        self
+    }
+
+    pub fn with_help(mut self, help: &str) -> Self {
+        self.help = Some(help.to_string());
+        self
+    }
+
+    //render the diagnostic with the labeled lines of source, file is only used for the
+    //--> line:column header
+    pub fn render(&self, file: &str, source: &str) -> String {
+        let mut output = format!("{}\n", self);
+
+        let mut labels = self.labels.iter().collect::<Vec<&Label>>();
+        labels.sort_by_key(|label| (label.position.line, label.position.start_column));
+
+        //the gutter is as wide as the largest line number shown in it
+        let width = labels
+            .last()
+            .map_or(0, |label| label.position.line.to_string().len());
+        let gutter = " ".repeat(width);
+
+        let primary = self
+            .labels
+            .iter()
+            .find(|label| label.primary)
+            .or(self.labels.first());
+
+        if let Some(primary) = primary {
+            output += &format!(
+                "{}--> {}:{}:{}\n{} |\n",
+                gutter, file, primary.position.line, primary.position.start_column, gutter
+            );
+        }
+
+        let lines = source.lines().collect::<Vec<&str>>();
+        let mut previous: Option<usize> = None;
+
+        for label in &labels {
+            let line = label.position.line;
+            let text = lines.get(line.wrapping_sub(1)).copied().unwrap_or("");
+
+            if previous != Some(line) {
+                if previous.is_some_and(|previous| line > previous + 1) {
+                    output += "...\n";
+                }
+
+                output += format!("{:>width$} | {}", line, text).trim_end();
+                output += "\n";
+                previous = Some(line);
+            }
+
+            output += format!("{} | {}", gutter, underline(label, text)).trim_end();
+            output += "\n";
+        }
+
+        if !labels.is_empty() && (!self.notes.is_empty() || self.help.is_some()) {
+            output += &format!("{} |\n", gutter);
+        }
+
+        for note in &self.notes {
+            output += &format!("{} = note: {}\n", gutter, note);
+        }
+
+        if let Some(help) = &self.help {
+            output += &format!("{} = help: {}\n", gutter, help);
+        }
+
+        output
+    }
+}
+
+//the marker line under a source line, tabs before the label are kept so it lines up
+//however wide the terminal draws them, an empty span still gets one marker
+fn underline(label: &Label, text: &str) -> String {
+    let start = label.position.start_column.saturating_sub(1);
+    let width = label
+        .position
+        .end_column
+        .saturating_sub(label.position.start_column)
+        .max(1);
+
+    let padding = text
+        .chars()
+        .chain(std::iter::repeat(' '))
+        .take(start)
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect::<String>();
+
+    let marker = if label.primary { "^" } else { "-" };
+
+    format!("{}{} {}", padding, marker.repeat(width), label.message)
+}
+
+//just the header line, use render for the source snippet
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.code {
+            Some(code) => write!(f, "{}[{}]: {}", self.severity, code, self.message),
+            None => write!(f, "{}: {}", self.severity, self.message),
+        }
+    }
+}
+
+impl From<&ParseError> for Diagnostic {
+    fn from(error: &ParseError) -> Self {
+        Diagnostic::from_error(&error.error, &error.position)
+    }
+}
+
+impl From<&TypeError> for Diagnostic {
+    fn from(error: &TypeError) -> Self {
+        Diagnostic::from_error(&error.error, &error.position)
+    }
+}
+
+impl From<&RuntimeError> for Diagnostic {
+    fn from(error: &RuntimeError) -> Self {
+        Diagnostic::from_error(&error.error, &error.position)
+    }
+}
@@ -0,0 +1,2169 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+use crate::ast::{
+    BinaryOp, Block, Expr, ExprKind, Field, FieldInit, MatchArm, Param, Pattern, PatternKind,
+    PostfixOp, Program, Stmt, UnaryOp, Variant,
+};
+use crate::{parse, Error, ErrorType, ParseError, Position, Type};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //run a program and return what it printed
+    fn output(source: &str) -> String {
+        let mut output = Vec::new();
+        Interpreter::with_output(&mut output).run(source).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    fn error(source: &str) -> RuntimeError {
+        Interpreter::with_output(io::sink())
+            .run(source)
+            .unwrap_err()
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(run("1 + 2 * 3;"), Ok(Value::Int(7)));
+        assert_eq!(run("(1 + 2) * 3;"), Ok(Value::Int(9)));
+        assert_eq!(run("7 / 2;"), Ok(Value::Int(3)));
+        assert_eq!(run("7 % 3;"), Ok(Value::Int(1)));
+        assert_eq!(run("7.0 / 2;"), Ok(Value::Float(3.5)));
+        assert_eq!(run("1 + 0.5;"), Ok(Value::Float(1.5)));
+        assert_eq!(run("-3 - -4;"), Ok(Value::Int(1)));
+        assert_eq!(
+            run("\"bs\" + \"-lang\";"),
+            Ok(Value::String("bs-lang".to_string()))
+        );
+    }
+
+    #[test]
+    fn comparison_and_logic() {
+        assert_eq!(run("1 + 2 * 3 == 7 && !false;"), Ok(Value::Bool(true)));
+        assert_eq!(run("1 < 2.5;"), Ok(Value::Bool(true)));
+        assert_eq!(run("3 >= 3 && 2 <= 1;"), Ok(Value::Bool(false)));
+        assert_eq!(run("2 == 2.0;"), Ok(Value::Bool(true)));
+        assert_eq!(run("\"a\" != \"b\";"), Ok(Value::Bool(true)));
+        assert_eq!(run("\"abc\" < \"abd\";"), Ok(Value::Bool(true)));
+        assert_eq!(run("[1, 2] == [1, 2];"), Ok(Value::Bool(true)));
+        assert_eq!(run("false || true;"), Ok(Value::Bool(true)));
+    }
+
+    #[test]
+    fn short_circuit() {
+        //the right side would fail if it was evaluated
+        assert_eq!(run("false && 1 / 0 == 0;"), Ok(Value::Bool(false)));
+        assert_eq!(run("true || 1 / 0 == 0;"), Ok(Value::Bool(true)));
+    }
+
+    #[test]
+    fn variables() {
+        assert_eq!(
+            run("let x: int = 2; let y = x * 10; y + 1;"),
+            Ok(Value::Int(21))
+        );
+        assert_eq!(run("let x: float = 10; x;"), Ok(Value::Float(10.0)));
+        assert_eq!(run("let x = 1; x = x + 1; x;"), Ok(Value::Int(2)));
+    }
+
+    #[test]
+    fn increment_and_decrement() {
+        assert_eq!(run("let i = 1; i++;"), Ok(Value::Int(1)));
+        assert_eq!(run("let i = 1; i++; i;"), Ok(Value::Int(2)));
+        assert_eq!(run("let i = 1; ++i;"), Ok(Value::Int(2)));
+        assert_eq!(run("let f = 1.5; f--; f;"), Ok(Value::Float(0.5)));
+    }
+
+    #[test]
+    fn lexical_scopes() {
+        assert_eq!(
+            run("let x = 1; { let x = 2; x = 3; } x;"),
+            Ok(Value::Int(1))
+        );
+        assert_eq!(run("let x = 1; { x = 5; } x;"), Ok(Value::Int(5)));
+
+        let error = error("{ let inner = 1; } inner;");
+
+        assert_eq!(
+            error,
+            RuntimeError::new(
+                Error::new(ErrorType::UndefinedVariable, "Undefined variable", "inner"),
+                Position::new(1, 20, 25).with_bytes(19, 24),
+            )
+        );
+    }
+
+    #[test]
+    fn constants() {
+        let error = error("const limit = 10;\nlimit = 11;");
+
+        assert_eq!(
+            error,
+            RuntimeError::new(
+                Error::new(
+                    ErrorType::InvalidAssignment,
+                    "Cannot assign to a constant",
+                    "limit"
+                ),
+                Position::new(2, 1, 6).with_bytes(18, 23),
+            )
+        );
+    }
+
+    #[test]
+    fn functions() {
+        let source = "
+func add(a: int, b: float): float {
+    return a + b;
+}
+add(1, 2.5);";
+
+        assert_eq!(run(source), Ok(Value::Float(3.5)));
+
+        let source = "
+func fib(n: int): int {
+    if n < 2 { return n; }
+    return fib(n - 1) + fib(n - 2);
+}
+fib(15);";
+
+        assert_eq!(run(source), Ok(Value::Int(610)));
+    }
+
+    #[test]
+    fn closures() {
+        let source = "
+let count = 0;
+func bump() { count++; }
+bump(); bump();
+count;";
+
+        assert_eq!(run(source), Ok(Value::Int(2)));
+    }
+
+    #[test]
+    fn control_flow() {
+        let source = "
+let total = 0;
+for (let i = 0; i < 10; i++) {
+    if i == 2 { continue; } else if i == 6 { break; }
+    total = total + i;
+}
+total;";
+
+        assert_eq!(run(source), Ok(Value::Int(13)));
+
+        let source = "
+let n = 0;
+while true { n++; if n >= 5 { break; } }
+n;";
+
+        assert_eq!(run(source), Ok(Value::Int(5)));
+
+        let source = "
+let sum = 0.0;
+for x in [1, 2.5, 3] { sum = sum + x; }
+sum;";
+
+        assert_eq!(run(source), Ok(Value::Float(6.5)));
+    }
+
+    #[test]
+    fn print() {
+        let source = "
+let name: string = \"bs\";
+print(name, 1, 2.0, true, [1, \"a\"]);
+print(len([1, 2, 3]));";
+
+        assert_eq!(output(source), "bs 1 2.0 true [1, \"a\"]\n3\n");
+    }
+
+    #[test]
+    fn arrays() {
+        let source = "let a = [1, 2, 3];\na[0] = a[2] * 10;\na[1]++;\nprint(a, a[1], \"bs\"[1]);\nlet grid = [[0, 0], [0, 0]];\ngrid[1][0] = 7;\nprint(grid);";
+
+        assert_eq!(output(source), "[30, 3, 3] 3 s\n[[0, 0], [7, 0]]\n");
+    }
+
+    #[test]
+    fn composite_annotations() {
+        let source = "let a: array<float> = [1, 2];\nlet m: int? = 3;\nfunc twice(f: func(int): int, x: int): int { return f(f(x)); }\nfunc inc(x: int): int { return x + 1; }\nprint(a, m, twice(inc, 1));";
+
+        assert_eq!(output(source), "[1.0, 2.0] 3 3\n");
+        assert_eq!(
+            error("let a: array<int> = [1, \"a\"];").error,
+            Error::new(
+                ErrorType::TypeMismatch,
+                "Expected array<int>, found array",
+                "a"
+            )
+        );
+    }
+
+    #[test]
+    fn declarations_are_hoisted() {
+        let source = "
+let p = Point { x: 1.0, y: 2.5 };
+print(p.len(), area(Shape::Square(2.0)));
+{
+    print(double(Size::Big));
+    func double(s: Size): int { return match s { Size::Big => 2, Size::Small => 0 }; }
+}
+enum Size { Big, Small }
+struct Point { x: float, y: float }
+impl Point {
+    func len(self): float { return self.x + self.y; }
+}
+func area(s: Shape): float { return match s { Shape::Square(a) => a * a }; }
+enum Shape { Square(float) }";
+
+        //what the type checker accepts has to run
+        assert_eq!(crate::check(&parse(source).unwrap()), vec![]);
+        assert_eq!(output(source), "3.5 4.0\n2\n");
+    }
+
+    #[test]
+    fn annotated_values() {
+        let source = "
+let a: array<int>= [1];
+let b: array<float?> = [a[0], 2.5];
+func first(values: array<float?>): float? { return values[0]; }
+let f: func(array<float?>): float? = first;
+print(f(b), b[1]);";
+
+        assert_eq!(crate::check(&parse(source).unwrap()), vec![]);
+        assert_eq!(output(source), "1.0 2.5\n");
+    }
+
+    #[test]
+    fn structs() {
+        let source = "struct Point { x: float, y: float }
+impl Point {
+    func len(self): float { return self.x * self.x + self.y * self.y; }
+    func shift(self, by: float) { self.x = self.x + by; }
+}
+let p = Point { y: 2, x: 1.0 };
+let q = p;
+q.shift(2);
+p.y++;
+print(p, p.len(), q.x);
+print(Point { x: 3, y: 3 } == p, [Point { x: 0, y: 0 }]);";
+
+        assert_eq!(
+            output(source),
+            "Point { x: 3.0, y: 3.0 } 18.0 3.0\ntrue [Point { x: 0.0, y: 0.0 }]\n"
+        );
+        assert_eq!(
+            output("struct Named { name: string, greet: func(string): string }\nfunc hi(s: string): string { return \"hi \" + s; }\nlet n = Named { name: \"bs\", greet: hi };\nprint(n.greet(n.name), n);"),
+            "hi bs Named { name: \"bs\", greet: <func hi> }\n"
+        );
+    }
+
+    #[test]
+    fn self_referencing_structs() {
+        let source = "struct B { f: array }
+let b = B { f: [] };
+b.f = [b];
+let c = B { f: [] };
+c.f = [c];
+print(b == b, b == c, b);";
+
+        assert_eq!(crate::check(&parse(source).unwrap()), vec![]);
+        assert_eq!(output(source), "true true B { f: [B { .. }] }\n");
+    }
+
+    #[test]
+    fn struct_errors() {
+        assert_eq!(
+            error("Point { x: 1 };").error,
+            Error::new(ErrorType::UndefinedType, "Undefined type", "Point")
+        );
+        assert_eq!(
+            error("struct P { x: int }\nP { x: 1, y: 2 };"),
+            RuntimeError::new(
+                Error::new(ErrorType::UndefinedField, "No field `y` on P", "y"),
+                Position::new(2, 11, 12).with_bytes(30, 31),
+            )
+        );
+        assert_eq!(
+            error("struct P { x: int, y: int }\nP { x: 1 };").error,
+            Error::new(ErrorType::MissingField, "Missing field `y`", "P")
+        );
+        assert_eq!(
+            error("struct P { x: int }\nP { x: \"a\" };").error,
+            Error::new(ErrorType::TypeMismatch, "Expected int, found string", "x")
+        );
+        assert_eq!(
+            error("struct P { x: int }\nlet p = P { x: 1 };\np.x = true;").error,
+            Error::new(ErrorType::TypeMismatch, "Expected int, found bool", "x")
+        );
+        assert_eq!(
+            error("let a = 1;\na.x;").error,
+            Error::new(ErrorType::UndefinedField, "No field `x` on int", "x")
+        );
+        assert_eq!(
+            error("impl P { }").error,
+            Error::new(ErrorType::UndefinedType, "Undefined type", "P")
+        );
+    }
+
+    #[test]
+    fn enums_and_match() {
+        let source = "enum Shape { Circle(float), Rect(float, float), Empty }
+impl Shape {
+    func area(self): float {
+        return match self {
+            Shape::Circle(r) => 3 * r * r,
+            Shape::Rect(w, h) if w == h => w * w,
+            Shape::Rect(w, h) => w * h,
+            Shape::Empty => 0,
+        };
+    }
+}
+let shapes = [Shape::Circle(1), Shape::Rect(2, 2), Shape::Rect(2, 3.5), Shape::Empty];
+for s in shapes { print(s, s.area()); }
+for n in [0, -1, 7] {
+    match n { 0 => print(\"zero\"), -1 => print(\"minus one\"), n if n > 5 => print(\"big\", n), _ => print(\"other\") }
+}
+enum Opt { Some(string), None }
+print(Opt::Some(\"a\"), Opt::None == Opt::None, Opt::Some(\"a\") == Opt::Some(\"b\"));";
+
+        assert_eq!(
+            output(source),
+            "Shape::Circle(1.0) 3.0\nShape::Rect(2.0, 2.0) 4.0\nShape::Rect(2.0, 3.5) 7.0\nShape::Empty 0.0\nzero\nminus one\nbig 7\nOpt::Some(\"a\") true false\n"
+        );
+    }
+
+    #[test]
+    fn match_errors() {
+        assert_eq!(
+            error("enum E { A, B }\nmatch E::B { E::A => 1 };"),
+            RuntimeError::new(
+                Error::new(
+                    ErrorType::NonExhaustiveMatch,
+                    "No match arm for E::B",
+                    "match"
+                ),
+                Position::new(2, 1, 25).with_bytes(16, 40),
+            )
+        );
+        assert_eq!(
+            error("enum E { A }\nE::C;").error,
+            Error::new(ErrorType::UndefinedVariant, "No variant `C` on E", "C")
+        );
+        assert_eq!(
+            error("enum E { A(int) }\nE::A;").error,
+            Error::new(
+                ErrorType::WrongArgumentCount,
+                "Expected 1 argument, found 0",
+                "A"
+            )
+        );
+        assert_eq!(
+            error("enum E { A(int) }\nE::A(\"x\");").error,
+            Error::new(ErrorType::TypeMismatch, "Expected int, found string", "A")
+        );
+        assert_eq!(
+            error("enum E { A(int) }\nmatch E::A(1) { E::A(x, y) => x };").error,
+            Error::new(
+                ErrorType::WrongArgumentCount,
+                "Expected 1 field, found 2",
+                "A"
+            )
+        );
+        assert_eq!(
+            error("match 1 { F::A => 1 };").error,
+            Error::new(ErrorType::UndefinedType, "Undefined type", "F")
+        );
+    }
+
+    #[test]
+    fn interpolation() {
+        let source = "let name = \"bs\";\nlet age = 2;\nprint(\"hello ${name}, you are ${age + 1} ${age / 4.0} ${age > 1}\");\nprint(\"${\"${age}\" + \"!\"}\");";
+
+        assert_eq!(output(source), "hello bs, you are 3 0.5 true\n2!\n");
+    }
+
+    #[test]
+    fn runtime_errors() {
+        assert_eq!(
+            error("let x = 1;\nx / 0;"),
+            RuntimeError::new(
+                Error::new(ErrorType::DivisionByZero, "Division by zero", "1 / 0"),
+                Position::new(2, 1, 6).with_bytes(11, 16),
+            )
+        );
+        assert_eq!(
+            error("1 + true;").error,
+            Error::new(
+                ErrorType::TypeMismatch,
+                "Cannot apply `+` to int and bool",
+                "+"
+            )
+        );
+        assert_eq!(
+            error("let x: int = \"a\";").error,
+            Error::new(ErrorType::TypeMismatch, "Expected int, found string", "x")
+        );
+        assert_eq!(
+            error("if 1 { }").error,
+            Error::new(
+                ErrorType::TypeMismatch,
+                "Expected a bool condition, found int",
+                "1"
+            )
+        );
+        assert_eq!(
+            error("func f(a: int) { } f();").error,
+            Error::new(
+                ErrorType::WrongArgumentCount,
+                "Expected 1 argument, found 0",
+                "f"
+            )
+        );
+        assert_eq!(
+            error("break;").error,
+            Error::new(
+                ErrorType::InvalidControlFlow,
+                "`break` outside of a loop",
+                "break"
+            )
+        );
+        assert_eq!(
+            error("9223372036854775807 + 1;").error,
+            Error::new(ErrorType::IntegerOverflow, "Integer overflow", "+")
+        );
+        assert_eq!(
+            error("let x = 9223372036854775807; x++;").error,
+            Error::new(ErrorType::IntegerOverflow, "Integer overflow", "+")
+        );
+        assert_eq!(
+            error("let x = -9223372036854775807 - 1; --x;").error,
+            Error::new(ErrorType::IntegerOverflow, "Integer overflow", "-")
+        );
+        assert_eq!(
+            error("let s = \"a\"; s++;").error,
+            Error::new(ErrorType::TypeMismatch, "Cannot apply `++` to string", "++")
+        );
+        assert_eq!(
+            error("let a = [1];\na[1];"),
+            RuntimeError::new(
+                Error::new(
+                    ErrorType::IndexOutOfBounds,
+                    "Index 1 out of bounds for length 1",
+                    "1"
+                ),
+                Position::new(2, 1, 5).with_bytes(13, 17),
+            )
+        );
+        assert_eq!(
+            error("let a = [1]; a[-1] = 2;").error,
+            Error::new(
+                ErrorType::IndexOutOfBounds,
+                "Index -1 out of bounds for length 1",
+                "-1"
+            )
+        );
+        assert_eq!(
+            error("\"${[1]}\";").error,
+            Error::new(ErrorType::TypeMismatch, "Cannot interpolate array", "${")
+        );
+        assert_eq!(
+            error("1[0];").error,
+            Error::new(ErrorType::TypeMismatch, "Cannot index int with int", "[")
+        );
+
+        assert_eq!(
+            error("func f(): int { return f(); } f();").error,
+            Error::new(ErrorType::StackOverflow, "Too many nested calls", "f")
+        );
+        //deep but ordinary recursion stays well inside the limit
+        assert_eq!(
+            output("func f(n: int): int { if n == 0 { return 0; } return f(n - 1) + 1; } print(f(900));"),
+            "900\n"
+        );
+
+        //each of these calls takes a lot of stack, depending on the build they either
+        //all fit or are stopped before the stack runs out
+        let deep = format!(
+            "func f(n: int): int {{ if n == 0 {{ return 0; }} return {}f(n - 1){}; }} f(999);",
+            "1 + (".repeat(50),
+            ")".repeat(50)
+        );
+
+        if let Err(error) = Interpreter::with_output(io::sink()).run(&deep) {
+            assert_eq!(error.error.error_type, ErrorType::StackOverflow);
+        }
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            error("let = 1;").error,
+            Error::new(
+                ErrorType::UnexpectedToken,
+                "Expected an identifier, found `=`",
+                "`=`"
+            )
+        );
+    }
+}
+
+//how deep calls may nest before the program is stopped
+const MAX_CALL_DEPTH: usize = 1000;
+
+//programs run on a thread with this much stack for every call they may nest, an
+//unoptimized build uses about 30kb for a call and more for calls inside deep expressions
+const STACK_PER_CALL: usize = 64 * 1024;
+
+//stack on top of that for the expressions and statements inside the deepest call, calls
+//are stopped once they leave less than this free
+const STACK_RESERVE: usize = 4 * 1024 * 1024;
+
+//a runtime value, one variant per entry in TYPES plus functions and the unit value
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Int(i64),
+    Float(f64),
+    String(String),
+    Bool(bool),
+    Array(Vec<Value>),
+    //structs are shared, assigning one to another variable does not copy the fields
+    Struct(Rc<RefCell<Instance>>),
+    Enum(Rc<EnumValue>),
+    Function(Rc<Function>),
+    Builtin(Builtin),
+    //the result of statements and functions that do not produce anything
+    Void,
+}
+
+impl Value {
+    pub fn type_name(&self) -> String {
+        match self {
+            Value::Int(_) => "int".to_string(),
+            Value::Float(_) => "float".to_string(),
+            Value::String(_) => "string".to_string(),
+            Value::Bool(_) => "bool".to_string(),
+            Value::Array(_) => "array".to_string(),
+            Value::Struct(instance) => instance.borrow().name.clone(),
+            Value::Enum(value) => value.enum_name.clone(),
+            Value::Function(_) | Value::Builtin(_) => "func".to_string(),
+            Value::Void => "void".to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_value(f, self, &mut Vec::new())
+    }
+}
+
+//visiting holds the structs being written, a struct that contains itself is written
+//as `Name { .. }` the second time it comes up
+fn write_value(
+    f: &mut fmt::Formatter,
+    value: &Value,
+    visiting: &mut Vec<*const RefCell<Instance>>,
+) -> fmt::Result {
+    match value {
+        Value::Int(value) => write!(f, "{}", value),
+        //debug formatting keeps the .0 on whole floats
+        Value::Float(value) => write!(f, "{:?}", value),
+        Value::String(value) => write!(f, "{}", value),
+        Value::Bool(value) => write!(f, "{}", value),
+        Value::Array(values) => {
+            write!(f, "[")?;
+
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+
+                write_nested(f, value, visiting)?;
+            }
+
+            write!(f, "]")
+        }
+        Value::Struct(instance) if visiting.contains(&Rc::as_ptr(instance)) => {
+            write!(f, "{} {{ .. }}", instance.borrow().name)
+        }
+        Value::Struct(rc) => {
+            let instance = rc.borrow();
+            write!(f, "{} {{", instance.name)?;
+            visiting.push(Rc::as_ptr(rc));
+
+            for (i, (name, value)) in instance.fields.iter().enumerate() {
+                write!(f, "{} {}: ", if i > 0 { "," } else { "" }, name)?;
+                write_nested(f, value, visiting)?;
+            }
+
+            visiting.pop();
+
+            if instance.fields.is_empty() {
+                write!(f, "}}")
+            } else {
+                write!(f, " }}")
+            }
+        }
+        Value::Enum(value) => {
+            write!(f, "{}::{}", value.enum_name, value.variant)?;
+
+            if value.values.is_empty() {
+                return Ok(());
+            }
+
+            write!(f, "(")?;
+
+            for (i, value) in value.values.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+
+                write_nested(f, value, visiting)?;
+            }
+
+            write!(f, ")")
+        }
+        Value::Function(function) => write!(f, "<func {}>", function.name),
+        Value::Builtin(builtin) => write!(f, "<func {}>", builtin.name()),
+        Value::Void => write!(f, "void"),
+    }
+}
+
+//strings inside arrays, structs and enum payloads are quoted so they can be told apart
+fn write_nested(
+    f: &mut fmt::Formatter,
+    value: &Value,
+    visiting: &mut Vec<*const RefCell<Instance>>,
+) -> fmt::Result {
+    match value {
+        Value::String(value) => write!(f, "{:?}", value),
+        value => write_value(f, value, visiting),
+    }
+}
+
+//the fields of a struct value in declaration order
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instance {
+    pub name: String,
+    pub fields: Vec<(String, Value)>,
+}
+
+impl Instance {
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.fields
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, value)| value)
+    }
+
+    fn get_mut(&mut self, name: &str) -> Option<&mut Value> {
+        self.fields
+            .iter_mut()
+            .find(|(field, _)| field == name)
+            .map(|(_, value)| value)
+    }
+}
+
+//one variant of an enum with its payload, enum values can not be changed once made
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumValue {
+    pub enum_name: String,
+    pub variant: String,
+    pub values: Vec<Value>,
+}
+
+//a user defined function together with the scope it was declared in
+pub struct Function {
+    pub name: String,
+    pub params: Vec<Param>,
+    pub return_ty: Option<Type>,
+    pub body: Block,
+    closure: Env,
+}
+
+//the closure can point back at the function itself, so only print the signature
+impl fmt::Debug for Function {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Function")
+            .field("name", &self.name)
+            .field("params", &self.params)
+            .field("return_ty", &self.return_ty)
+            .finish()
+    }
+}
+
+//two function values are only equal if they are the same declaration
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+//functions every program can call without declaring them
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Builtin {
+    Print,
+    Len,
+}
+
+impl Builtin {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "print" => Some(Builtin::Print),
+            "len" => Some(Builtin::Len),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Builtin::Print => "print",
+            Builtin::Len => "len",
+        }
+    }
+}
+
+//an error that stopped the program and where it happened
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeError {
+    pub error: Error,
+    pub position: Position,
+}
+
+impl RuntimeError {
+    pub fn new(error: Error, position: Position) -> Self {
+        RuntimeError { error, position }
+    }
+}
+
+impl From<ParseError> for RuntimeError {
+    fn from(error: ParseError) -> Self {
+        RuntimeError::new(error.error, error.position)
+    }
+}
+
+fn runtime_error(
+    error_type: ErrorType,
+    message: &str,
+    error: &str,
+    position: &Position,
+) -> RuntimeError {
+    RuntimeError::new(Error::new(error_type, message, error), position.clone())
+}
+
+//parse and run a program, printing to stdout
+pub fn run(source: &str) -> Result<Value, RuntimeError> {
+    Interpreter::new().run(source)
+}
+
+#[derive(Debug)]
+struct Binding {
+    value: Value,
+    mutable: bool,
+}
+
+#[derive(Debug, Default)]
+struct Scope {
+    bindings: HashMap<String, Binding>,
+    parent: Option<Env>,
+}
+
+type Env = Rc<RefCell<Scope>>;
+
+fn child_scope(parent: &Env) -> Env {
+    Rc::new(RefCell::new(Scope {
+        bindings: HashMap::new(),
+        parent: Some(parent.clone()),
+    }))
+}
+
+fn define(env: &Env, name: &str, value: Value, mutable: bool) {
+    env.borrow_mut()
+        .bindings
+        .insert(name.to_string(), Binding { value, mutable });
+}
+
+fn lookup(env: &Env, name: &str) -> Option<Value> {
+    let scope = env.borrow();
+
+    match scope.bindings.get(name) {
+        Some(binding) => Some(binding.value.clone()),
+        None => scope
+            .parent
+            .as_ref()
+            .and_then(|parent| lookup(parent, name)),
+    }
+}
+
+//walk up the scopes to the binding and overwrite it, Err(false) if it is missing and
+//Err(true) if it is a constant
+fn assign(env: &Env, name: &str, value: Value) -> Result<(), bool> {
+    let mut scope = env.borrow_mut();
+
+    match scope.bindings.get_mut(name) {
+        Some(binding) if binding.mutable => {
+            binding.value = value;
+            Ok(())
+        }
+        Some(_) => Err(true),
+        None => match &scope.parent {
+            Some(parent) => assign(parent, name, value),
+            None => Err(false),
+        },
+    }
+}
+
+//carries the interpreter to the thread running a program and the result back, which is
+//sound as the Rc values inside are only ever used by one thread at a time: the caller
+//waits for the thread to finish before it touches them again
+struct Handoff<T>(T);
+
+unsafe impl<T> Send for Handoff<T> {}
+
+impl<T> Handoff<T> {
+    //taking self whole makes a closure capture the Handoff and not just the value in it
+    fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+//what a statement asks the enclosing loop or function to do next
+enum Flow {
+    Normal,
+    Break(Position),
+    Continue(Position),
+    Return(Value, Position),
+}
+
+pub struct Interpreter<'a> {
+    globals: Env,
+    output: Box<dyn Write + 'a>,
+    depth: usize,
+    //where the stack of the running program started
+    stack_start: usize,
+    //struct and enum declarations and their methods by type name
+    structs: HashMap<String, Vec<Field>>,
+    enums: HashMap<String, Vec<Variant>>,
+    methods: HashMap<String, HashMap<String, Rc<Function>>>,
+}
+
+impl Interpreter<'static> {
+    pub fn new() -> Self {
+        Interpreter::with_output(io::stdout())
+    }
+}
+
+impl Default for Interpreter<'static> {
+    fn default() -> Self {
+        Interpreter::new()
+    }
+}
+
+impl<'a> Interpreter<'a> {
+    //print writes to output instead of stdout
+    pub fn with_output(output: impl Write + 'a) -> Self {
+        Interpreter {
+            globals: Env::default(),
+            output: Box::new(output),
+            depth: 0,
+            stack_start: 0,
+            structs: HashMap::new(),
+            enums: HashMap::new(),
+            methods: HashMap::new(),
+        }
+    }
+
+    //parse and run a program, globals are kept between runs
+    pub fn run(&mut self, source: &str) -> Result<Value, RuntimeError> {
+        let program = parse(source)?;
+        self.execute(&program)
+    }
+
+    //run a parsed program, the result is the value of a trailing expression statement
+    //
+    //every call is a few rust calls deep, so the program runs on a thread with enough
+    //stack to reach MAX_CALL_DEPTH whatever thread this is called from
+    pub fn execute(&mut self, program: &Program) -> Result<Value, RuntimeError> {
+        let handoff = Handoff((self, program));
+
+        let result = std::thread::scope(|scope| {
+            std::thread::Builder::new()
+                .stack_size(MAX_CALL_DEPTH * STACK_PER_CALL + STACK_RESERVE)
+                .spawn_scoped(scope, move || {
+                    let (interpreter, program) = handoff.into_inner();
+                    Handoff(interpreter.execute_here(program))
+                })
+                .expect("failed to start the thread running the program")
+                .join()
+        });
+
+        match result {
+            Ok(result) => result.into_inner(),
+            Err(panic) => std::panic::resume_unwind(panic),
+        }
+    }
+
+    fn execute_here(&mut self, program: &Program) -> Result<Value, RuntimeError> {
+        self.stack_start = stack_address();
+        let globals = self.globals.clone();
+        let mut result = Value::Void;
+
+        self.declare(&program.statements, &globals)?;
+
+        for stmt in &program.statements {
+            result = Value::Void;
+
+            let flow = match stmt {
+                Stmt::Expr { expr, .. } => {
+                    result = self.eval(expr, &globals)?;
+                    Flow::Normal
+                }
+                stmt => self.exec(stmt, &globals)?,
+            };
+
+            check_outside_loop(&flow)?;
+
+            if let Flow::Return(_, position) = flow {
+                return Err(runtime_error(
+                    ErrorType::InvalidControlFlow,
+                    "`return` outside of a function",
+                    "return",
+                    &position,
+                ));
+            }
+        }
+
+        Ok(result)
+    }
+
+    //every statement kind with more than a line or two of work gets its own method,
+    //this keeps the frames of the recursive exec and eval small in debug builds
+    fn exec(&mut self, stmt: &Stmt, env: &Env) -> Result<Flow, RuntimeError> {
+        match stmt {
+            Stmt::Let { name, ty, init, .. } => {
+                self.exec_declaration(name, ty.as_ref(), init, true, env)?;
+                Ok(Flow::Normal)
+            }
+            Stmt::Const { name, ty, init, .. } => {
+                self.exec_declaration(name, ty.as_ref(), init, false, env)?;
+                Ok(Flow::Normal)
+            }
+            //these were set up by declare before the block started
+            Stmt::Func { .. } | Stmt::Struct { .. } | Stmt::Enum { .. } | Stmt::Impl { .. } => {
+                Ok(Flow::Normal)
+            }
+            Stmt::Return { value, position } => {
+                let value = match value {
+                    Some(value) => self.eval(value, env)?,
+                    None => Value::Void,
+                };
+
+                Ok(Flow::Return(value, position.clone()))
+            }
+            Stmt::Expr { expr, .. } => {
+                self.eval(expr, env)?;
+                Ok(Flow::Normal)
+            }
+            Stmt::Block(block) => self.exec_block(block, env),
+            Stmt::If {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                if self.eval_condition(condition, env)? {
+                    self.exec_block(then_branch, env)
+                } else if let Some(else_branch) = else_branch {
+                    self.exec(else_branch, env)
+                } else {
+                    Ok(Flow::Normal)
+                }
+            }
+            Stmt::While {
+                condition, body, ..
+            } => self.exec_while(condition, body, env),
+            Stmt::For {
+                init,
+                condition,
+                update,
+                body,
+                ..
+            } => self.exec_for(
+                init.as_deref(),
+                condition.as_ref(),
+                update.as_ref(),
+                body,
+                env,
+            ),
+            Stmt::ForIn {
+                name,
+                iterable,
+                body,
+                ..
+            } => self.exec_for_in(name, iterable, body, env),
+            Stmt::Break { position } => Ok(Flow::Break(position.clone())),
+            Stmt::Continue { position } => Ok(Flow::Continue(position.clone())),
+            Stmt::Error { position } => Err(syntax_error(position)),
+        }
+    }
+
+    fn exec_declaration(
+        &mut self,
+        name: &str,
+        ty: Option<&Type>,
+        init: &Expr,
+        mutable: bool,
+        env: &Env,
+    ) -> Result<(), RuntimeError> {
+        let value = self.eval(init, env)?;
+        let value = check_type(value, ty, name, &init.position)?;
+        define(env, name, value, mutable);
+        Ok(())
+    }
+
+    //types, functions and methods may be used before the statement declaring them, as the
+    //type checker allows, so those of a block are all set up before its first statement runs
+    fn declare(&mut self, statements: &[Stmt], env: &Env) -> Result<(), RuntimeError> {
+        for stmt in statements {
+            match stmt {
+                Stmt::Struct { name, fields, .. } => {
+                    self.structs.insert(name.clone(), fields.clone());
+                }
+                Stmt::Enum { name, variants, .. } => {
+                    self.enums.insert(name.clone(), variants.clone());
+                }
+                _ => {}
+            }
+        }
+
+        for stmt in statements {
+            match stmt {
+                Stmt::Func {
+                    name,
+                    params,
+                    return_ty,
+                    body,
+                    ..
+                } => {
+                    let function = Function {
+                        name: name.clone(),
+                        params: params.clone(),
+                        return_ty: return_ty.clone(),
+                        body: body.clone(),
+                        closure: env.clone(),
+                    };
+
+                    define(env, name, Value::Function(Rc::new(function)), false);
+                }
+                Stmt::Impl {
+                    name,
+                    name_position,
+                    methods,
+                    ..
+                } => self.exec_impl(name, name_position, methods, env)?,
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn exec_impl(
+        &mut self,
+        name: &str,
+        position: &Position,
+        methods: &[Stmt],
+        env: &Env,
+    ) -> Result<(), RuntimeError> {
+        if !self.structs.contains_key(name) && !self.enums.contains_key(name) {
+            return Err(runtime_error(
+                ErrorType::UndefinedType,
+                "Undefined type",
+                name,
+                position,
+            ));
+        }
+
+        let table = self.methods.entry(name.to_string()).or_default();
+
+        for method in methods {
+            if let Stmt::Func {
+                name,
+                params,
+                return_ty,
+                body,
+                ..
+            } = method
+            {
+                let function = Function {
+                    name: name.clone(),
+                    params: params.clone(),
+                    return_ty: return_ty.clone(),
+                    body: body.clone(),
+                    closure: env.clone(),
+                };
+
+                table.insert(name.clone(), Rc::new(function));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn exec_while(
+        &mut self,
+        condition: &Expr,
+        body: &Block,
+        env: &Env,
+    ) -> Result<Flow, RuntimeError> {
+        while self.eval_condition(condition, env)? {
+            match self.exec_block(body, env)? {
+                Flow::Break(_) => break,
+                Flow::Return(value, position) => return Ok(Flow::Return(value, position)),
+                Flow::Normal | Flow::Continue(_) => {}
+            }
+        }
+
+        Ok(Flow::Normal)
+    }
+
+    fn exec_for(
+        &mut self,
+        init: Option<&Stmt>,
+        condition: Option<&Expr>,
+        update: Option<&Expr>,
+        body: &Block,
+        env: &Env,
+    ) -> Result<Flow, RuntimeError> {
+        //the loop variable lives in its own scope around the body
+        let env = &child_scope(env);
+
+        if let Some(init) = init {
+            self.exec(init, env)?;
+        }
+
+        loop {
+            if let Some(condition) = condition {
+                if !self.eval_condition(condition, env)? {
+                    break;
+                }
+            }
+
+            match self.exec_block(body, env)? {
+                Flow::Break(_) => break,
+                Flow::Return(value, position) => return Ok(Flow::Return(value, position)),
+                Flow::Normal | Flow::Continue(_) => {}
+            }
+
+            if let Some(update) = update {
+                self.eval(update, env)?;
+            }
+        }
+
+        Ok(Flow::Normal)
+    }
+
+    fn exec_for_in(
+        &mut self,
+        name: &str,
+        iterable: &Expr,
+        body: &Block,
+        env: &Env,
+    ) -> Result<Flow, RuntimeError> {
+        let values = match self.eval(iterable, env)? {
+            Value::Array(values) => values,
+            Value::String(value) => value
+                .chars()
+                .map(|c| Value::String(c.to_string()))
+                .collect(),
+            value => {
+                return Err(runtime_error(
+                    ErrorType::TypeMismatch,
+                    &format!("Cannot iterate over {}", value.type_name()),
+                    name,
+                    &iterable.position,
+                ))
+            }
+        };
+
+        for value in values {
+            let env = &child_scope(env);
+            define(env, name, value, true);
+
+            match self.exec_block(body, env)? {
+                Flow::Break(_) => break,
+                Flow::Return(value, position) => return Ok(Flow::Return(value, position)),
+                Flow::Normal | Flow::Continue(_) => {}
+            }
+        }
+
+        Ok(Flow::Normal)
+    }
+
+    fn exec_block(&mut self, block: &Block, env: &Env) -> Result<Flow, RuntimeError> {
+        let env = &child_scope(env);
+        self.declare(&block.statements, env)?;
+
+        for stmt in &block.statements {
+            match self.exec(stmt, env)? {
+                Flow::Normal => {}
+                flow => return Ok(flow),
+            }
+        }
+
+        Ok(Flow::Normal)
+    }
+
+    fn eval_condition(&mut self, condition: &Expr, env: &Env) -> Result<bool, RuntimeError> {
+        match self.eval(condition, env)? {
+            Value::Bool(value) => Ok(value),
+            value => Err(runtime_error(
+                ErrorType::TypeMismatch,
+                &format!("Expected a bool condition, found {}", value.type_name()),
+                &value.to_string(),
+                &condition.position,
+            )),
+        }
+    }
+
+    fn eval(&mut self, expr: &Expr, env: &Env) -> Result<Value, RuntimeError> {
+        match &expr.kind {
+            ExprKind::Int(value) => Ok(Value::Int(*value)),
+            ExprKind::Float(value) => Ok(Value::Float(*value)),
+            ExprKind::String(value) => Ok(Value::String(value.clone())),
+            ExprKind::Bool(value) => Ok(Value::Bool(*value)),
+            ExprKind::Array(elements) => Ok(Value::Array(self.eval_all(elements, env)?)),
+            ExprKind::Interpolation(parts) => self.eval_interpolation(parts, env),
+            ExprKind::Identifier(name) => match lookup(env, name) {
+                Some(value) => Ok(value),
+                None => match Builtin::from_name(name) {
+                    Some(builtin) => Ok(Value::Builtin(builtin)),
+                    None => Err(runtime_error(
+                        ErrorType::UndefinedVariable,
+                        "Undefined variable",
+                        name,
+                        &expr.position,
+                    )),
+                },
+            },
+            ExprKind::Unary { op, operand } => self.eval_unary(*op, operand, &expr.position, env),
+            ExprKind::Postfix { op, operand } => {
+                let (old, _) = self.step(operand, *op == PostfixOp::Increment, op.as_str(), env)?;
+                Ok(old)
+            }
+            ExprKind::Binary { op, left, right } => {
+                self.eval_binary(*op, left, right, &expr.position, env)
+            }
+            ExprKind::Call { callee, args } => self.eval_call(callee, args, &expr.position, env),
+            ExprKind::StructLiteral { name, fields } => {
+                self.eval_struct_literal(name, fields, &expr.position, env)
+            }
+            ExprKind::Field { target, name } => {
+                let target = self.eval(target, env)?;
+                field(&target, name, &expr.position)
+            }
+            ExprKind::Variant {
+                enum_name,
+                variant,
+                args,
+            } => self.eval_variant(enum_name, variant, args, &expr.position, env),
+            ExprKind::Match { subject, arms } => {
+                self.eval_match(subject, arms, &expr.position, env)
+            }
+            ExprKind::Index { target, index } => {
+                let target = self.eval(target, env)?;
+                let index = self.eval(index, env)?;
+
+                match (target, index) {
+                    (Value::Array(values), Value::Int(index)) => {
+                        let index = element(values.len(), index, &expr.position)?;
+                        Ok(values[index].clone())
+                    }
+                    (Value::String(value), Value::Int(index)) => {
+                        let chars: Vec<char> = value.chars().collect();
+                        let index = element(chars.len(), index, &expr.position)?;
+                        Ok(Value::String(chars[index].to_string()))
+                    }
+                    (target, index) => Err(index_mismatch(&target, &index, &expr.position)),
+                }
+            }
+            ExprKind::Error => Err(syntax_error(&expr.position)),
+            ExprKind::Assign { target, value } => {
+                let value = self.eval(value, env)?;
+                self.assign(target, value.clone(), env)?;
+                Ok(value)
+            }
+        }
+    }
+
+    //concatenate the parts, converting ints, floats and bools to their printed form
+    fn eval_interpolation(&mut self, parts: &[Expr], env: &Env) -> Result<Value, RuntimeError> {
+        let mut result = String::new();
+
+        for part in parts {
+            match self.eval(part, env)? {
+                value @ (Value::String(_) | Value::Int(_) | Value::Float(_) | Value::Bool(_)) => {
+                    result.push_str(&value.to_string());
+                }
+                value => {
+                    return Err(runtime_error(
+                        ErrorType::TypeMismatch,
+                        &format!("Cannot interpolate {}", value.type_name()),
+                        "${",
+                        &part.position,
+                    ))
+                }
+            }
+        }
+
+        Ok(Value::String(result))
+    }
+
+    //target.name(args) calls a method when the struct has no field by that name
+    fn eval_call(
+        &mut self,
+        callee: &Expr,
+        args: &[Expr],
+        position: &Position,
+        env: &Env,
+    ) -> Result<Value, RuntimeError> {
+        if let ExprKind::Field { target, name } = &callee.kind {
+            let target = self.eval(target, env)?;
+
+            if let Some(method) = self.method(&target, name) {
+                let mut values = vec![target];
+                values.extend(self.eval_all(args, env)?);
+
+                return self.call(Value::Function(method), values, position);
+            }
+
+            let function = field(&target, name, &callee.position)?;
+            let args = self.eval_all(args, env)?;
+
+            return self.call(function, args, position);
+        }
+
+        let function = self.eval(callee, env)?;
+        let args = self.eval_all(args, env)?;
+
+        self.call(function, args, position)
+    }
+
+    fn method(&self, target: &Value, name: &str) -> Option<Rc<Function>> {
+        let type_name = match target {
+            Value::Struct(instance) => {
+                let instance = instance.borrow();
+
+                if instance.get(name).is_some() {
+                    return None;
+                }
+
+                instance.name.clone()
+            }
+            Value::Enum(value) => value.enum_name.clone(),
+            _ => return None,
+        };
+
+        self.methods.get(&type_name)?.get(name).cloned()
+    }
+
+    fn eval_struct_literal(
+        &mut self,
+        name: &str,
+        inits: &[FieldInit],
+        position: &Position,
+        env: &Env,
+    ) -> Result<Value, RuntimeError> {
+        let Some(fields) = self.structs.get(name).cloned() else {
+            return Err(runtime_error(
+                ErrorType::UndefinedType,
+                "Undefined type",
+                name,
+                position,
+            ));
+        };
+
+        if let Some(init) = inits
+            .iter()
+            .find(|init| !fields.iter().any(|field| field.name == init.name))
+        {
+            return Err(no_field(name, &init.name, &init.position));
+        }
+
+        let mut values = Vec::new();
+
+        //fields are stored in declaration order, whatever order the literal uses
+        for field in &fields {
+            let Some(init) = inits.iter().find(|init| init.name == field.name) else {
+                return Err(runtime_error(
+                    ErrorType::MissingField,
+                    &format!("Missing field `{}`", field.name),
+                    name,
+                    position,
+                ));
+            };
+
+            let value = self.eval(&init.value, env)?;
+            let value = check_type(value, Some(&field.ty), &field.name, &init.value.position)?;
+
+            values.push((field.name.clone(), value));
+        }
+
+        Ok(Value::Struct(Rc::new(RefCell::new(Instance {
+            name: name.to_string(),
+            fields: values,
+        }))))
+    }
+
+    fn eval_variant(
+        &mut self,
+        enum_name: &str,
+        name: &str,
+        args: &[Expr],
+        position: &Position,
+        env: &Env,
+    ) -> Result<Value, RuntimeError> {
+        let variant = self.variant(enum_name, name, position)?;
+
+        if variant.fields.len() != args.len() {
+            return Err(argument_count(
+                name,
+                variant.fields.len(),
+                args.len(),
+                position,
+            ));
+        }
+
+        let mut values = Vec::new();
+
+        for (arg, ty) in args.iter().zip(&variant.fields) {
+            let value = self.eval(arg, env)?;
+            values.push(check_type(value, Some(ty), name, &arg.position)?);
+        }
+
+        Ok(Value::Enum(Rc::new(EnumValue {
+            enum_name: enum_name.to_string(),
+            variant: name.to_string(),
+            values,
+        })))
+    }
+
+    fn variant(
+        &self,
+        enum_name: &str,
+        name: &str,
+        position: &Position,
+    ) -> Result<Variant, RuntimeError> {
+        let Some(variants) = self.enums.get(enum_name) else {
+            return Err(runtime_error(
+                ErrorType::UndefinedType,
+                "Undefined type",
+                enum_name,
+                position,
+            ));
+        };
+
+        match variants.iter().find(|variant| variant.name == name) {
+            Some(variant) => Ok(variant.clone()),
+            None => Err(runtime_error(
+                ErrorType::UndefinedVariant,
+                &format!("No variant `{}` on {}", name, enum_name),
+                name,
+                position,
+            )),
+        }
+    }
+
+    //the first arm whose pattern and guard match is taken, its bindings only live in that arm
+    fn eval_match(
+        &mut self,
+        subject: &Expr,
+        arms: &[MatchArm],
+        position: &Position,
+        env: &Env,
+    ) -> Result<Value, RuntimeError> {
+        let value = self.eval(subject, env)?;
+
+        for arm in arms {
+            let scope = child_scope(env);
+
+            if !self.bind_pattern(&arm.pattern, &value, &scope)? {
+                continue;
+            }
+
+            if let Some(guard) = &arm.guard {
+                if !self.eval_condition(guard, &scope)? {
+                    continue;
+                }
+            }
+
+            return self.eval(&arm.body, &scope);
+        }
+
+        Err(runtime_error(
+            ErrorType::NonExhaustiveMatch,
+            &format!("No match arm for {}", value),
+            "match",
+            position,
+        ))
+    }
+
+    //whether the value fits the pattern, names in the pattern are defined in scope
+    fn bind_pattern(
+        &mut self,
+        pattern: &Pattern,
+        value: &Value,
+        scope: &Env,
+    ) -> Result<bool, RuntimeError> {
+        match &pattern.kind {
+            PatternKind::Wildcard => Ok(true),
+            PatternKind::Binding(name) => {
+                define(scope, name, value.clone(), true);
+                Ok(true)
+            }
+            PatternKind::Literal(literal) => {
+                let literal = self.eval(literal, scope)?;
+                Ok(equals(&literal, value))
+            }
+            PatternKind::Variant {
+                enum_name,
+                variant,
+                fields,
+            } => {
+                let declared = self.variant(enum_name, variant, &pattern.position)?;
+
+                if declared.fields.len() != fields.len() {
+                    return Err(field_count(
+                        variant,
+                        declared.fields.len(),
+                        fields.len(),
+                        &pattern.position,
+                    ));
+                }
+
+                let Value::Enum(value) = value else {
+                    return Ok(false);
+                };
+
+                if value.enum_name != *enum_name || value.variant != *variant {
+                    return Ok(false);
+                }
+
+                for (field, value) in fields.iter().zip(&value.values) {
+                    if !self.bind_pattern(field, value, scope)? {
+                        return Ok(false);
+                    }
+                }
+
+                Ok(true)
+            }
+        }
+    }
+
+    fn eval_all(&mut self, exprs: &[Expr], env: &Env) -> Result<Vec<Value>, RuntimeError> {
+        exprs.iter().map(|expr| self.eval(expr, env)).collect()
+    }
+
+    fn eval_unary(
+        &mut self,
+        op: UnaryOp,
+        operand: &Expr,
+        position: &Position,
+        env: &Env,
+    ) -> Result<Value, RuntimeError> {
+        match op {
+            UnaryOp::Neg => match self.eval(operand, env)? {
+                Value::Int(value) => value.checked_neg().map(Value::Int).ok_or_else(|| {
+                    runtime_error(
+                        ErrorType::IntegerOverflow,
+                        "Integer overflow",
+                        "-",
+                        position,
+                    )
+                }),
+                Value::Float(value) => Ok(Value::Float(-value)),
+                value => Err(unary_mismatch(op.as_str(), &value, position)),
+            },
+            UnaryOp::Not => match self.eval(operand, env)? {
+                Value::Bool(value) => Ok(Value::Bool(!value)),
+                value => Err(unary_mismatch(op.as_str(), &value, position)),
+            },
+            UnaryOp::Increment | UnaryOp::Decrement => {
+                let (_, new) = self.step(operand, op == UnaryOp::Increment, op.as_str(), env)?;
+                Ok(new)
+            }
+        }
+    }
+
+    fn eval_binary(
+        &mut self,
+        op: BinaryOp,
+        left: &Expr,
+        right: &Expr,
+        position: &Position,
+        env: &Env,
+    ) -> Result<Value, RuntimeError> {
+        let left = self.eval(left, env)?;
+
+        //&& and || only look at the right side if they have to
+        if let (BinaryOp::And | BinaryOp::Or, Value::Bool(value)) = (op, &left) {
+            if *value == (op == BinaryOp::Or) {
+                return Ok(Value::Bool(*value));
+            }
+        }
+
+        let right = self.eval(right, env)?;
+        binary(op, left, right, position)
+    }
+
+    //add or subtract one from a variable, returns the old and the new value
+    fn step(
+        &mut self,
+        target: &Expr,
+        up: bool,
+        op: &str,
+        env: &Env,
+    ) -> Result<(Value, Value), RuntimeError> {
+        let old = self.eval(target, env)?;
+        let one = Value::Int(1);
+
+        let new = if up {
+            binary(BinaryOp::Add, old.clone(), one, &target.position)
+        } else {
+            binary(BinaryOp::Sub, old.clone(), one, &target.position)
+        }
+        .map_err(|error| match error.error.error_type {
+            //the mismatch is about the operator written, not the `+` or `-` behind it
+            ErrorType::TypeMismatch => unary_mismatch(op, &old, &target.position),
+            _ => error,
+        })?;
+
+        self.assign(target, new.clone(), env)?;
+        Ok((old, new))
+    }
+
+    fn assign(&mut self, target: &Expr, value: Value, env: &Env) -> Result<(), RuntimeError> {
+        //arrays are values, so a[i] = v rebuilds a and assigns the whole array back
+        if let ExprKind::Index {
+            target: array,
+            index,
+        } = &target.kind
+        {
+            let container = self.eval(array, env)?;
+            let index = self.eval(index, env)?;
+
+            let Value::Array(mut values) = container else {
+                return Err(index_mismatch(&container, &index, &target.position));
+            };
+            let Value::Int(index) = index else {
+                return Err(index_mismatch(
+                    &Value::Array(values),
+                    &index,
+                    &target.position,
+                ));
+            };
+
+            let index = element(values.len(), index, &target.position)?;
+            values[index] = value;
+            return self.assign(array, Value::Array(values), env);
+        }
+
+        //structs are shared, so the field is changed in place
+        if let ExprKind::Field {
+            target: instance,
+            name,
+        } = &target.kind
+        {
+            let instance = self.eval(instance, env)?;
+            return self.assign_field(instance, name, value, &target.position);
+        }
+
+        let ExprKind::Identifier(name) = &target.kind else {
+            return Err(runtime_error(
+                ErrorType::InvalidAssignment,
+                "Can only assign to a variable",
+                "",
+                &target.position,
+            ));
+        };
+
+        match assign(env, name, value) {
+            Ok(()) => Ok(()),
+            Err(true) => Err(runtime_error(
+                ErrorType::InvalidAssignment,
+                "Cannot assign to a constant",
+                name,
+                &target.position,
+            )),
+            Err(false) => Err(runtime_error(
+                ErrorType::UndefinedVariable,
+                "Undefined variable",
+                name,
+                &target.position,
+            )),
+        }
+    }
+
+    fn assign_field(
+        &mut self,
+        instance: Value,
+        name: &str,
+        value: Value,
+        position: &Position,
+    ) -> Result<(), RuntimeError> {
+        let Value::Struct(instance) = instance else {
+            return Err(no_field(&instance.type_name(), name, position));
+        };
+
+        let mut instance = instance.borrow_mut();
+        let ty = self.structs.get(&instance.name).and_then(|fields| {
+            fields
+                .iter()
+                .find(|field| field.name == name)
+                .map(|field| &field.ty)
+        });
+
+        let value = check_type(value, ty, name, position)?;
+
+        match instance.get_mut(name) {
+            Some(slot) => {
+                *slot = value;
+                Ok(())
+            }
+            None => Err(no_field(&instance.name, name, position)),
+        }
+    }
+
+    fn call(
+        &mut self,
+        function: Value,
+        args: Vec<Value>,
+        position: &Position,
+    ) -> Result<Value, RuntimeError> {
+        let function = match function {
+            Value::Function(function) => function,
+            Value::Builtin(builtin) => return self.call_builtin(builtin, args, position),
+            value => {
+                return Err(runtime_error(
+                    ErrorType::TypeMismatch,
+                    &format!("Cannot call {}", value.type_name()),
+                    &value.to_string(),
+                    position,
+                ))
+            }
+        };
+
+        if args.len() != function.params.len() {
+            return Err(argument_count(
+                &function.name,
+                function.params.len(),
+                args.len(),
+                position,
+            ));
+        }
+
+        //calls inside deep expressions can use up the stack before reaching the limit
+        let stack_used = self.stack_start.abs_diff(stack_address());
+
+        if self.depth >= MAX_CALL_DEPTH || stack_used > MAX_CALL_DEPTH * STACK_PER_CALL {
+            return Err(runtime_error(
+                ErrorType::StackOverflow,
+                "Too many nested calls",
+                &function.name,
+                position,
+            ));
+        }
+
+        let env = &child_scope(&function.closure);
+
+        for (param, arg) in function.params.iter().zip(args) {
+            let value = check_type(arg, Some(&param.ty), &param.name, position)?;
+            define(env, &param.name, value, true);
+        }
+
+        self.depth += 1;
+        let flow = self.exec_block(&function.body, env);
+        self.depth -= 1;
+
+        let flow = flow?;
+        check_outside_loop(&flow)?;
+
+        let (value, position) = match flow {
+            Flow::Return(value, position) => (value, position),
+            _ => (Value::Void, function.body.position.clone()),
+        };
+
+        match &function.return_ty {
+            Some(ty) => check_type(value, Some(ty), &function.name, &position),
+            None => Ok(value),
+        }
+    }
+
+    fn call_builtin(
+        &mut self,
+        builtin: Builtin,
+        args: Vec<Value>,
+        position: &Position,
+    ) -> Result<Value, RuntimeError> {
+        match builtin {
+            Builtin::Print => {
+                let line = args
+                    .iter()
+                    .map(|arg| arg.to_string())
+                    .collect::<Vec<String>>()
+                    .join(" ");
+
+                writeln!(self.output, "{}", line).map_err(|error| {
+                    runtime_error(
+                        ErrorType::Io,
+                        "Could not write output",
+                        &error.to_string(),
+                        position,
+                    )
+                })?;
+
+                Ok(Value::Void)
+            }
+            Builtin::Len => match args.as_slice() {
+                [Value::Array(values)] => Ok(Value::Int(values.len() as i64)),
+                [Value::String(value)] => Ok(Value::Int(value.chars().count() as i64)),
+                [value] => Err(runtime_error(
+                    ErrorType::TypeMismatch,
+                    &format!("Cannot take the length of {}", value.type_name()),
+                    "len",
+                    position,
+                )),
+                _ => Err(argument_count("len", 1, args.len(), position)),
+            },
+        }
+    }
+}
+
+//the address of a local, the distance between two of them is the stack used in between
+fn stack_address() -> usize {
+    let marker = 0u8;
+    std::ptr::addr_of!(marker) as usize
+}
+
+//break and continue may not leave the loop they are in
+fn check_outside_loop(flow: &Flow) -> Result<(), RuntimeError> {
+    match flow {
+        Flow::Break(position) => Err(runtime_error(
+            ErrorType::InvalidControlFlow,
+            "`break` outside of a loop",
+            "break",
+            position,
+        )),
+        Flow::Continue(position) => Err(runtime_error(
+            ErrorType::InvalidControlFlow,
+            "`continue` outside of a loop",
+            "continue",
+            position,
+        )),
+        _ => Ok(()),
+    }
+}
+
+//check a value against an annotation, ints are widened where a float is expected
+fn check_type(
+    value: Value,
+    ty: Option<&Type>,
+    name: &str,
+    position: &Position,
+) -> Result<Value, RuntimeError> {
+    let Some(ty) = ty else {
+        return Ok(value);
+    };
+
+    let found = value.type_name();
+
+    conform(value, ty).ok_or_else(|| {
+        runtime_error(
+            ErrorType::TypeMismatch,
+            &format!("Expected {}, found {}", ty, found),
+            name,
+            position,
+        )
+    })
+}
+
+//the value converted to fit the annotation, None if it does not fit
+fn conform(value: Value, ty: &Type) -> Option<Value> {
+    match (ty, value) {
+        (Type::Int, value @ Value::Int(_))
+        | (Type::Float, value @ Value::Float(_))
+        | (Type::String, value @ Value::String(_))
+        | (Type::Bool, value @ Value::Bool(_))
+        | (Type::Array(None), value @ Value::Array(_)) => Some(value),
+        (Type::Float, Value::Int(value)) => Some(Value::Float(value as f64)),
+        (Type::Array(Some(element)), Value::Array(values)) => values
+            .into_iter()
+            .map(|value| conform(value, element))
+            .collect::<Option<Vec<Value>>>()
+            .map(Value::Array),
+        (Type::Optional(inner), value) => conform(value, inner),
+        //signatures are the type checker's job, at runtime only the arity is compared
+        (Type::Func(params, _), Value::Function(function)) => {
+            (function.params.len() == params.len()).then_some(Value::Function(function))
+        }
+        (Type::Func(..), value @ Value::Builtin(_)) => Some(value),
+        (Type::Named(name), Value::Struct(instance)) if instance.borrow().name == *name => {
+            Some(Value::Struct(instance))
+        }
+        (Type::Named(name), Value::Enum(value)) if value.enum_name == *name => {
+            Some(Value::Enum(value))
+        }
+        _ => None,
+    }
+}
+
+//programs from Parser::parse_recovering may still hold the placeholders of broken code
+fn syntax_error(position: &Position) -> RuntimeError {
+    runtime_error(
+        ErrorType::UnexpectedToken,
+        "Cannot run code that failed to parse",
+        "error",
+        position,
+    )
+}
+
+fn argument_count(name: &str, expected: usize, found: usize, position: &Position) -> RuntimeError {
+    let plural = if expected == 1 { "" } else { "s" };
+
+    runtime_error(
+        ErrorType::WrongArgumentCount,
+        &format!("Expected {} argument{}, found {}", expected, plural, found),
+        name,
+        position,
+    )
+}
+
+fn field_count(variant: &str, expected: usize, found: usize, position: &Position) -> RuntimeError {
+    let plural = if expected == 1 { "" } else { "s" };
+
+    runtime_error(
+        ErrorType::WrongArgumentCount,
+        &format!("Expected {} field{}, found {}", expected, plural, found),
+        variant,
+        position,
+    )
+}
+
+fn unary_mismatch(op: &str, value: &Value, position: &Position) -> RuntimeError {
+    runtime_error(
+        ErrorType::TypeMismatch,
+        &format!("Cannot apply `{}` to {}", op, value.type_name()),
+        op,
+        position,
+    )
+}
+
+fn field(target: &Value, name: &str, position: &Position) -> Result<Value, RuntimeError> {
+    if let Value::Struct(instance) = target {
+        if let Some(value) = instance.borrow().get(name) {
+            return Ok(value.clone());
+        }
+    }
+
+    Err(no_field(&target.type_name(), name, position))
+}
+
+fn no_field(type_name: &str, name: &str, position: &Position) -> RuntimeError {
+    runtime_error(
+        ErrorType::UndefinedField,
+        &format!("No field `{}` on {}", name, type_name),
+        name,
+        position,
+    )
+}
+
+fn index_mismatch(target: &Value, index: &Value, position: &Position) -> RuntimeError {
+    runtime_error(
+        ErrorType::TypeMismatch,
+        &format!(
+            "Cannot index {} with {}",
+            target.type_name(),
+            index.type_name()
+        ),
+        "[",
+        position,
+    )
+}
+
+//turns an index into a vector offset, anything outside 0..len is an error
+fn element(len: usize, index: i64, position: &Position) -> Result<usize, RuntimeError> {
+    match usize::try_from(index) {
+        Ok(index) if index < len => Ok(index),
+        _ => Err(runtime_error(
+            ErrorType::IndexOutOfBounds,
+            &format!("Index {} out of bounds for length {}", index, len),
+            &index.to_string(),
+            position,
+        )),
+    }
+}
+
+fn binary(
+    op: BinaryOp,
+    left: Value,
+    right: Value,
+    position: &Position,
+) -> Result<Value, RuntimeError> {
+    let mismatch = |left: &Value, right: &Value| {
+        runtime_error(
+            ErrorType::TypeMismatch,
+            &format!(
+                "Cannot apply `{}` to {} and {}",
+                op.as_str(),
+                left.type_name(),
+                right.type_name()
+            ),
+            op.as_str(),
+            position,
+        )
+    };
+
+    match op {
+        BinaryOp::Eq => Ok(Value::Bool(equals(&left, &right))),
+        BinaryOp::NotEq => Ok(Value::Bool(!equals(&left, &right))),
+        BinaryOp::And | BinaryOp::Or => match (&left, &right) {
+            //the left side was already checked by eval, so the result is the right side
+            (Value::Bool(_), Value::Bool(value)) => Ok(Value::Bool(*value)),
+            _ => Err(mismatch(&left, &right)),
+        },
+        BinaryOp::Less | BinaryOp::Greater | BinaryOp::LessEq | BinaryOp::GreaterEq => {
+            let ordering = match (&left, &right) {
+                (Value::String(a), Value::String(b)) => a.partial_cmp(b),
+                _ => match (as_float(&left), as_float(&right)) {
+                    (Some(a), Some(b)) => a.partial_cmp(&b),
+                    _ => return Err(mismatch(&left, &right)),
+                },
+            };
+
+            //comparisons with NaN are always false
+            let Some(ordering) = ordering else {
+                return Ok(Value::Bool(false));
+            };
+
+            Ok(Value::Bool(match op {
+                BinaryOp::Less => ordering.is_lt(),
+                BinaryOp::Greater => ordering.is_gt(),
+                BinaryOp::LessEq => ordering.is_le(),
+                _ => ordering.is_ge(),
+            }))
+        }
+        BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem => {
+            match (&left, &right) {
+                (Value::Int(a), Value::Int(b)) => {
+                    if matches!(op, BinaryOp::Div | BinaryOp::Rem) && *b == 0 {
+                        return Err(runtime_error(
+                            ErrorType::DivisionByZero,
+                            "Division by zero",
+                            &format!("{} {} {}", left, op.as_str(), right),
+                            position,
+                        ));
+                    }
+
+                    let result = match op {
+                        BinaryOp::Add => a.checked_add(*b),
+                        BinaryOp::Sub => a.checked_sub(*b),
+                        BinaryOp::Mul => a.checked_mul(*b),
+                        BinaryOp::Div => a.checked_div(*b),
+                        _ => a.checked_rem(*b),
+                    };
+
+                    result.map(Value::Int).ok_or_else(|| {
+                        runtime_error(
+                            ErrorType::IntegerOverflow,
+                            "Integer overflow",
+                            op.as_str(),
+                            position,
+                        )
+                    })
+                }
+                (Value::String(a), Value::String(b)) if op == BinaryOp::Add => {
+                    Ok(Value::String(a.clone() + b))
+                }
+                _ => match (as_float(&left), as_float(&right)) {
+                    (Some(a), Some(b)) => Ok(Value::Float(match op {
+                        BinaryOp::Add => a + b,
+                        BinaryOp::Sub => a - b,
+                        BinaryOp::Mul => a * b,
+                        BinaryOp::Div => a / b,
+                        _ => a % b,
+                    })),
+                    _ => Err(mismatch(&left, &right)),
+                },
+            }
+        }
+    }
+}
+
+//ints and floats compare by value, everything else structurally
+fn equals(left: &Value, right: &Value) -> bool {
+    equals_visiting(left, right, &mut Vec::new())
+}
+
+//visiting holds the pairs of structs being compared, meeting a pair again means it
+//contains itself and is equal as far as the comparison got
+fn equals_visiting(
+    left: &Value,
+    right: &Value,
+    visiting: &mut Vec<(*const RefCell<Instance>, *const RefCell<Instance>)>,
+) -> bool {
+    match (left, right) {
+        (Value::Int(_), Value::Float(_)) | (Value::Float(_), Value::Int(_)) => {
+            as_float(left) == as_float(right)
+        }
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .zip(b)
+                    .all(|(a, b)| equals_visiting(a, b, visiting))
+        }
+        (Value::Struct(a), Value::Struct(b)) if Rc::ptr_eq(a, b) => true,
+        (Value::Struct(a), Value::Struct(b)) => {
+            let pair = (Rc::as_ptr(a), Rc::as_ptr(b));
+
+            if visiting.contains(&pair) {
+                return true;
+            }
+
+            let (a, b) = (a.borrow(), b.borrow());
+            visiting.push(pair);
+
+            let equal = a.name == b.name
+                && a.fields
+                    .iter()
+                    .zip(&b.fields)
+                    .all(|((_, a), (_, b))| equals_visiting(a, b, visiting));
+
+            visiting.pop();
+            equal
+        }
+        (Value::Enum(a), Value::Enum(b)) => {
+            a.enum_name == b.enum_name
+                && a.variant == b.variant
+                && a.values
+                    .iter()
+                    .zip(&b.values)
+                    .all(|(a, b)| equals_visiting(a, b, visiting))
+        }
+        _ => left == right,
+    }
+}
+
+fn as_float(value: &Value) -> Option<f64> {
+    match value {
+        Value::Int(value) => Some(*value as f64),
+        Value::Float(value) => Some(*value),
+        _ => None,
+    }
+}
@@ -2,6 +2,24 @@
 //ignore unused imports for now
 #[allow(unused_imports)]
 use std::fs;
+use std::fmt;
+use std::iter::FusedIterator;
+
+pub mod ast;
+pub mod cst;
+pub mod diagnostic;
+pub mod interpreter;
+pub mod parser;
+pub mod source;
+pub mod typeck;
+pub mod xid;
+
+pub use cst::{parse_cst, NodeKind, SyntaxElement, SyntaxNode, SyntaxToken};
+pub use diagnostic::{Diagnostic, Label, Severity};
+pub use interpreter::{run, Interpreter, RuntimeError, Value};
+pub use parser::{parse, ParseError, Parser};
+pub use source::{FileId, Location, SourceMap, Span};
+pub use typeck::{check, infer, Ty, TypeError, TypeInfo};
 
 #[cfg(test)]
 mod tests {
@@ -10,14 +28,10 @@ mod tests {
     #[test]
     fn tokens() {
         //write a really long string
-        let mut tokenizer =
+        let tokenizer =
             Tokenizer::new("let x: float = 10 + 20.1; let y: int = 30 + 40; let z: float = x + y; \n let x: string = \"hello world\"; let r: bool = true; 
 let a: array = [1, 2, 3.4]");
-        let mut tokens = Vec::new();
-
-        while let Some(token) = tokenizer.next() {
-            tokens.push(token);
-        }
+        let tokens = tokenizer.collect::<Vec<Token>>();
 
         //write to file for debugging
         //fs::write("tokens.txt", format!("{:#?}", tokens)).expect("Unable to write file");
@@ -26,130 +40,210 @@ let a: array = [1, 2, 3.4]");
         assert_eq!(tokens[0].kind, TokenKind::Keyword(Keyword::new("let")));
         assert_eq!(
             tokens[1].kind,
-            TokenKind::Identifier(Identifier::new("x", Type::new("float")))
+            TokenKind::Identifier(Identifier::new("x"))
         );
-        assert_eq!(tokens[2].kind, TokenKind::Punctuator(Punctuator::new("=")));
-        assert_eq!(tokens[3].kind, TokenKind::IntLiteral(IntLiteral::new("10")));
-        assert_eq!(tokens[4].kind, TokenKind::Operator(Operator::new("+")));
+        assert_eq!(tokens[2].kind, TokenKind::Punctuator(Punctuator::new(":")));
+        assert_eq!(tokens[3].kind, TokenKind::Type(Type::new("float")));
+        assert_eq!(tokens[4].kind, TokenKind::Punctuator(Punctuator::new("=")));
+        assert_eq!(tokens[5].kind, TokenKind::IntLiteral(IntLiteral(10)));
+        assert_eq!(tokens[6].kind, TokenKind::Operator(Operator::new("+")));
         assert_eq!(
-            tokens[5].kind,
-            TokenKind::FloatLiteral(FloatLiteral::new("20.1"))
+            tokens[7].kind,
+            TokenKind::FloatLiteral(FloatLiteral(20.1))
         );
-        assert_eq!(tokens[6].kind, TokenKind::Punctuator(Punctuator::new(";")));
-        assert_eq!(tokens[7].kind, TokenKind::Keyword(Keyword::new("let")));
+        assert_eq!(tokens[8].kind, TokenKind::Punctuator(Punctuator::new(";")));
+        assert_eq!(tokens[9].kind, TokenKind::Keyword(Keyword::new("let")));
         assert_eq!(
-            tokens[8].kind,
-            TokenKind::Identifier(Identifier::new("y", Type::new("int")))
+            tokens[10].kind,
+            TokenKind::Identifier(Identifier::new("y"))
         );
-        assert_eq!(tokens[9].kind, TokenKind::Punctuator(Punctuator::new("=")));
-        assert_eq!(tokens[10].kind, TokenKind::IntLiteral(IntLiteral::new("30")));
-        assert_eq!(tokens[11].kind, TokenKind::Operator(Operator::new("+")));
+        assert_eq!(tokens[11].kind, TokenKind::Punctuator(Punctuator::new(":")));
+        assert_eq!(tokens[12].kind, TokenKind::Type(Type::new("int")));
+        assert_eq!(tokens[13].kind, TokenKind::Punctuator(Punctuator::new("=")));
+        assert_eq!(tokens[14].kind, TokenKind::IntLiteral(IntLiteral(30)));
+        assert_eq!(tokens[15].kind, TokenKind::Operator(Operator::new("+")));
         assert_eq!(
-            tokens[12].kind,
-            TokenKind::IntLiteral(IntLiteral::new("40"))
+            tokens[16].kind,
+            TokenKind::IntLiteral(IntLiteral(40))
         );
-        assert_eq!(tokens[13].kind, TokenKind::Punctuator(Punctuator::new(";")));
-        assert_eq!(tokens[14].kind, TokenKind::Keyword(Keyword::new("let")));
+        assert_eq!(tokens[17].kind, TokenKind::Punctuator(Punctuator::new(";")));
+        assert_eq!(tokens[18].kind, TokenKind::Keyword(Keyword::new("let")));
         assert_eq!(
-            tokens[15].kind,
-            TokenKind::Identifier(Identifier::new("z", Type::new("float")))
+            tokens[19].kind,
+            TokenKind::Identifier(Identifier::new("z"))
         );
-        assert_eq!(tokens[16].kind, TokenKind::Punctuator(Punctuator::new("=")));
+        assert_eq!(tokens[20].kind, TokenKind::Punctuator(Punctuator::new(":")));
+        assert_eq!(tokens[21].kind, TokenKind::Type(Type::new("float")));
+        assert_eq!(tokens[22].kind, TokenKind::Punctuator(Punctuator::new("=")));
         assert_eq!(
-            tokens[17].kind,
-            TokenKind::Identifier(Identifier::new("x", Type::new("")))
+            tokens[23].kind,
+            TokenKind::Identifier(Identifier::new("x"))
         );
-        assert_eq!(tokens[18].kind, TokenKind::Operator(Operator::new("+")));
+        assert_eq!(tokens[24].kind, TokenKind::Operator(Operator::new("+")));
         assert_eq!(
-            tokens[19].kind,
-            TokenKind::Identifier(Identifier::new("y", Type::new("")))
+            tokens[25].kind,
+            TokenKind::Identifier(Identifier::new("y"))
         );
-        assert_eq!(tokens[20].kind, TokenKind::Punctuator(Punctuator::new(";")));
-        assert_eq!(tokens[21].kind, TokenKind::Keyword(Keyword::new("let")));
+        assert_eq!(tokens[26].kind, TokenKind::Punctuator(Punctuator::new(";")));
+        assert_eq!(tokens[27].kind, TokenKind::Keyword(Keyword::new("let")));
         assert_eq!(
-            tokens[22].kind,
-            TokenKind::Identifier(Identifier::new("x", Type::new("string")))
+            tokens[28].kind,
+            TokenKind::Identifier(Identifier::new("x"))
         );
-        assert_eq!(tokens[23].kind, TokenKind::Punctuator(Punctuator::new("=")));
+        assert_eq!(tokens[29].kind, TokenKind::Punctuator(Punctuator::new(":")));
+        assert_eq!(tokens[30].kind, TokenKind::Type(Type::new("string")));
+        assert_eq!(tokens[31].kind, TokenKind::Punctuator(Punctuator::new("=")));
         assert_eq!(
-            tokens[24].kind,
+            tokens[32].kind,
             TokenKind::StringLiteral(StringLiteral::new("hello world"))
         );
-        assert_eq!(tokens[25].kind, TokenKind::Punctuator(Punctuator::new(";")));
-        assert_eq!(tokens[26].kind, TokenKind::Keyword(Keyword::new("let")));
+        assert_eq!(tokens[33].kind, TokenKind::Punctuator(Punctuator::new(";")));
+        assert_eq!(tokens[34].kind, TokenKind::Keyword(Keyword::new("let")));
         assert_eq!(
-            tokens[27].kind,
-            TokenKind::Identifier(Identifier::new("r", Type::new("bool")))
+            tokens[35].kind,
+            TokenKind::Identifier(Identifier::new("r"))
         );
-        assert_eq!(tokens[28].kind, TokenKind::Punctuator(Punctuator::new("=")));
+        assert_eq!(tokens[36].kind, TokenKind::Punctuator(Punctuator::new(":")));
+        assert_eq!(tokens[37].kind, TokenKind::Type(Type::new("bool")));
+        assert_eq!(tokens[38].kind, TokenKind::Punctuator(Punctuator::new("=")));
         assert_eq!(
-            tokens[29].kind,
+            tokens[39].kind,
             TokenKind::BoolLiteral(BoolLiteral::new("true"))
         );
 
-        assert_eq!(tokens[30].kind, TokenKind::Punctuator(Punctuator::new(";")));
-        assert_eq!(tokens[31].kind, TokenKind::Keyword(Keyword::new("let")));
+        assert_eq!(tokens[40].kind, TokenKind::Punctuator(Punctuator::new(";")));
+        assert_eq!(tokens[41].kind, TokenKind::Keyword(Keyword::new("let")));
         assert_eq!(
-            tokens[32].kind,
-            TokenKind::Identifier(Identifier::new("a", Type::new("array")))
+            tokens[42].kind,
+            TokenKind::Identifier(Identifier::new("a"))
         );
-        assert_eq!(tokens[33].kind, TokenKind::Punctuator(Punctuator::new("=")));
+        assert_eq!(tokens[43].kind, TokenKind::Punctuator(Punctuator::new(":")));
+        assert_eq!(tokens[44].kind, TokenKind::Type(Type::new("array")));
+        assert_eq!(tokens[45].kind, TokenKind::Punctuator(Punctuator::new("=")));
+
+        let expected = [
+            Token::new(TokenKind::Punctuator(Punctuator("[".to_string())), 3, 1 + 15, 2 + 15).with_bytes(139, 140),
+            Token::new(TokenKind::IntLiteral(IntLiteral(1)), 3, 2 + 15, 3 + 15).with_bytes(140, 141),
+            Token::new(TokenKind::Punctuator(Punctuator(",".to_string())), 3, 3 + 15, 4 + 15).with_bytes(141, 142),
+            Token::new(TokenKind::IntLiteral(IntLiteral(2)), 3, 5  + 15, 6  + 15).with_bytes(143, 144),
+            Token::new(TokenKind::Punctuator(Punctuator(",".to_string())), 3, 6  + 15, 7  + 15).with_bytes(144, 145),
+            Token::new(TokenKind::FloatLiteral(FloatLiteral(3.4)), 3, 8  + 15, 11  + 15).with_bytes(146, 149),
+            Token::new(TokenKind::Punctuator(Punctuator("]".to_string())), 3, 11 + 15, 12 + 15).with_bytes(149, 150),
+        ];
+
+        assert_eq!(tokens[46..53], expected);
 
-        let expected = [Token::new(
-            TokenKind::ArrayLiteral(
-                ArrayLiteral::new(vec![
-                    Token::new(TokenKind::IntLiteral(IntLiteral(1)), 3, 2 + 15, 3 + 15),
-                    Token::new(TokenKind::Punctuator(Punctuator(",".to_string())), 3, 3 + 15, 4 + 15),
-                    Token::new(TokenKind::IntLiteral(IntLiteral(2)), 3, 5  + 15, 6  + 15),
-                    Token::new(TokenKind::Punctuator(Punctuator(",".to_string())), 3, 6  + 15, 7  + 15),
-                    Token::new(TokenKind::FloatLiteral(FloatLiteral(3.4)), 3, 8  + 15, 11  + 15),
-                ])
-            ),
-            3,
-            1 + 15,
-            12 + 15,
-        )];
+    }
 
-        assert_eq!(tokens[34], expected[0]);
+    #[test]
+    fn int() {
+        let tokenizer = Tokenizer::new("10");
+        let tokens = tokenizer.collect::<Vec<Token>>();
 
+        assert_eq!(tokens[0].kind, TokenKind::IntLiteral(IntLiteral(10)));
     }
 
     #[test]
-    fn int() {
-        let mut tokenizer = Tokenizer::new("10");
-        let mut tokens = Vec::new();
+    fn float() {
+        let tokenizer = Tokenizer::new("10.1");
+        let tokens = tokenizer.collect::<Vec<Token>>();
 
-        while let Some(token) = tokenizer.next() {
-            tokens.push(token);
-        }
+        assert_eq!(
+            tokens[0].kind,
+            TokenKind::FloatLiteral(FloatLiteral(10.1))
+        );
+    }
+
+    #[test]
+    fn number_prefixes() {
+        let values = ["0xff", "0xDead_Beef", "0b1010", "0o755", "1_000_000", "0"]
+            .map(|source| Tokenizer::new(source).next().unwrap().kind);
 
-        assert_eq!(tokens[0].kind, TokenKind::IntLiteral(IntLiteral::new("10")));
+        assert_eq!(
+            values,
+            [255, 0xdead_beef, 10, 493, 1_000_000, 0].map(|value| TokenKind::IntLiteral(IntLiteral(value)))
+        );
     }
 
     #[test]
-    fn float() {
-        let mut tokenizer = Tokenizer::new("10.1");
-        let mut tokens = Vec::new();
+    fn exponents() {
+        let values = ["1e10", "2.5e-3", "1E+2", "6.022_140e2_3", "1_0.5"]
+            .map(|source| Tokenizer::new(source).next().unwrap().kind);
 
-        while let Some(token) = tokenizer.next() {
-            tokens.push(token);
-        }
+        assert_eq!(
+            values,
+            [1e10, 2.5e-3, 1e2, 6.022140e23, 10.5].map(|value| TokenKind::FloatLiteral(FloatLiteral(value)))
+        );
+    }
+
+    #[test]
+    fn dots_next_to_numbers() {
+        let tokenizer = Tokenizer::new("1. .5 1.5.2");
+        let tokens = tokenizer.map(|token| token.kind).collect::<Vec<TokenKind>>();
 
         assert_eq!(
-            tokens[0].kind,
-            TokenKind::FloatLiteral(FloatLiteral::new("10.1"))
+            tokens,
+            vec![
+                TokenKind::IntLiteral(IntLiteral(1)),
+                TokenKind::Punctuator(Punctuator::new(".")),
+                TokenKind::Punctuator(Punctuator::new(".")),
+                TokenKind::IntLiteral(IntLiteral(5)),
+                TokenKind::FloatLiteral(FloatLiteral(1.5)),
+                TokenKind::Punctuator(Punctuator::new(".")),
+                TokenKind::IntLiteral(IntLiteral(2)),
+                TokenKind::Eof,
+            ]
         );
     }
 
     #[test]
-    fn string() {
-        let mut tokenizer = Tokenizer::new("\"Hello World\"");
-        let mut tokens = Vec::new();
+    fn number_errors() {
+        let tokenizer = Tokenizer::new("x = 99999999999999999999 + 1;");
+        let tokens = tokenizer.collect::<Vec<Token>>();
 
-        while let Some(token) = tokenizer.next() {
-            tokens.push(token);
+        assert_eq!(
+            tokens[2],
+            Token::new(
+                TokenKind::Error(Error::new(
+                    ErrorType::IntegerOverflow,
+                    "Integer literal out of range",
+                    "99999999999999999999"
+                )),
+                1,
+                5,
+                25,
+            ).with_bytes(4, 24)
+        );
+        assert_eq!(tokens[3].kind, TokenKind::Operator(Operator::new("+")));
+
+        for source in ["0x", "0b102", "0xZZ", "1_", "1__", "1e", "1.5e", "1.5_", "12abc", "0x_1"] {
+            assert_eq!(
+                Tokenizer::new(source).next().unwrap().kind,
+                TokenKind::Error(Error::new(ErrorType::MalformedNumber, "Malformed number", source)),
+                "{}",
+                source
+            );
         }
 
+        assert_eq!(
+            Tokenizer::new("0x8000_0000_0000_0000").next().unwrap().kind,
+            TokenKind::Error(Error::new(
+                ErrorType::IntegerOverflow,
+                "Integer literal out of range",
+                "0x8000_0000_0000_0000"
+            ))
+        );
+        assert_eq!(
+            Tokenizer::new("1e400").next().unwrap().kind,
+            TokenKind::Error(Error::new(ErrorType::FloatOverflow, "Float literal out of range", "1e400"))
+        );
+    }
+
+    #[test]
+    fn string() {
+        let tokenizer = Tokenizer::new("\"Hello World\"");
+        let tokens = tokenizer.collect::<Vec<Token>>();
+
         assert_eq!(
             tokens[0].kind,
             TokenKind::StringLiteral(StringLiteral::new("Hello World"))
@@ -157,95 +251,743 @@ let a: array = [1, 2, 3.4]");
     }
 
     #[test]
-    fn keyword() {
-        let mut tokenizer = Tokenizer::new("let");
-        let mut tokens = Vec::new();
+    fn string_escapes() {
+        let tokenizer = Tokenizer::new(r#""a\n\t\\\"\0\u{48}\u{1F600}b""#);
+        let tokens = tokenizer.collect::<Vec<Token>>();
+
+        assert_eq!(
+            tokens[0],
+            Token::new(
+                TokenKind::StringLiteral(StringLiteral::new("a\n\t\\\"\0H\u{1F600}b")),
+                1,
+                1,
+                30,
+            ).with_bytes(0, 29)
+        );
+        assert_eq!(tokens[1].kind, TokenKind::Eof);
+    }
 
-        while let Some(token) = tokenizer.next() {
-            tokens.push(token);
+    #[test]
+    fn unterminated_string() {
+        let tokenizer = Tokenizer::new("let s = \"abc");
+        let tokens = tokenizer.collect::<Vec<Token>>();
+
+        assert_eq!(
+            tokens[3],
+            Token::new(
+                TokenKind::Error(Error::new(
+                    ErrorType::UnterminatedString,
+                    "Unterminated string",
+                    "\"abc"
+                )),
+                1,
+                9,
+                13,
+            ).with_bytes(8, 12)
+        );
+        assert_eq!(tokens[4].kind, TokenKind::Eof);
+
+        //a backslash right before the end of input does not escape anything
+        let tokens = Tokenizer::new("\"abc\\").collect::<Vec<Token>>();
+
+        assert_eq!(
+            tokens[0].kind,
+            TokenKind::Error(Error::new(
+                ErrorType::UnterminatedString,
+                "Unterminated string",
+                "\"abc\\"
+            ))
+        );
+    }
+
+    #[test]
+    fn invalid_escape() {
+        let tokenizer = Tokenizer::new(r#"x = "a\qb\zc"; y"#);
+        let tokens = tokenizer.collect::<Vec<Token>>();
+
+        assert_eq!(
+            tokens[2],
+            Token::new(
+                TokenKind::Error(Error::new(
+                    ErrorType::InvalidEscape,
+                    "Invalid escape sequence",
+                    "\\q"
+                ).with_position(Position::new(1, 7, 9).with_bytes(6, 8))),
+                1,
+                5,
+                14,
+            ).with_bytes(4, 13)
+        );
+        assert_eq!(tokens[3].kind, TokenKind::Punctuator(Punctuator::new(";")));
+
+        for source in [r#""\u{}""#, r#""\u{110000}""#, r#""\u{D800}""#, r#""\u41""#] {
+            let tokens = Tokenizer::new(source).collect::<Vec<Token>>();
+
+            assert!(
+                matches!(&tokens[0].kind, TokenKind::Error(error) if error.error_type == ErrorType::InvalidEscape),
+                "{}",
+                source
+            );
         }
+    }
+
+    #[test]
+    fn interpolation() {
+        let tokenizer = Tokenizer::new(r#""hi ${name}, ${age + 1}!""#);
+        let tokens = tokenizer.collect::<Vec<Token>>();
+
+        assert_eq!(
+            tokens,
+            vec![
+                Token::new(TokenKind::TemplateHead(StringLiteral::new("hi ")), 1, 1, 7).with_bytes(0, 6),
+                Token::new(TokenKind::Identifier(Identifier::new("name")), 1, 7, 11).with_bytes(6, 10),
+                Token::new(TokenKind::TemplateMiddle(StringLiteral::new(", ")), 1, 11, 16).with_bytes(10, 15),
+                Token::new(TokenKind::Identifier(Identifier::new("age")), 1, 16, 19).with_bytes(15, 18),
+                Token::new(TokenKind::Operator(Operator::new("+")), 1, 20, 21).with_bytes(19, 20),
+                Token::new(TokenKind::IntLiteral(IntLiteral(1)), 1, 22, 23).with_bytes(21, 22),
+                Token::new(TokenKind::TemplateTail(StringLiteral::new("!")), 1, 23, 26).with_bytes(22, 25),
+                Token::new(TokenKind::Eof, 1, 26, 26).with_bytes(25, 25),
+            ]
+        );
+    }
+
+    #[test]
+    fn nested_interpolation() {
+        let tokenizer = Tokenizer::new(r#""a ${ { "b ${c}" } } \${d} $e" }"#);
+        let kinds = tokenizer.map(|token| token.kind).collect::<Vec<TokenKind>>();
+
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::TemplateHead(StringLiteral::new("a ")),
+                TokenKind::Punctuator(Punctuator::new("{")),
+                TokenKind::TemplateHead(StringLiteral::new("b ")),
+                TokenKind::Identifier(Identifier::new("c")),
+                TokenKind::TemplateTail(StringLiteral::new("")),
+                TokenKind::Punctuator(Punctuator::new("}")),
+                TokenKind::TemplateTail(StringLiteral::new(" ${d} $e")),
+                TokenKind::Punctuator(Punctuator::new("}")),
+                TokenKind::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn unterminated_interpolation() {
+        let tokenizer = Tokenizer::new(r#""a ${x} b"#);
+        let tokens = tokenizer.collect::<Vec<Token>>();
+
+        assert_eq!(
+            tokens[2],
+            Token::new(
+                TokenKind::Error(Error::new(
+                    ErrorType::UnterminatedString,
+                    "Unterminated string",
+                    "} b"
+                )),
+                1,
+                7,
+                10,
+            ).with_bytes(6, 9)
+        );
+    }
+
+    #[test]
+    fn keyword() {
+        let tokenizer = Tokenizer::new("let");
+        let tokens = tokenizer.collect::<Vec<Token>>();
 
         assert_eq!(tokens[0].kind, TokenKind::Keyword(Keyword::new("let")));
     }
 
     #[test]
     fn identifier() {
-        let mut tokenizer = Tokenizer::new("x: int");
-        let mut tokens = Vec::new();
+        let tokenizer = Tokenizer::new("x: int");
+        let tokens = tokenizer.collect::<Vec<Token>>();
 
-        while let Some(token) = tokenizer.next() {
-            tokens.push(token);
-        }
+        assert_eq!(
+            tokens,
+            vec![
+                Token::new(TokenKind::Identifier(Identifier::new("x")), 1, 1, 2).with_bytes(0, 1),
+                Token::new(TokenKind::Punctuator(Punctuator::new(":")), 1, 2, 3).with_bytes(1, 2),
+                Token::new(TokenKind::Type(Type::new("int")), 1, 4, 7).with_bytes(3, 6),
+                Token::new(TokenKind::Eof, 1, 7, 7).with_bytes(6, 6),
+            ]
+        );
+    }
+
+    #[test]
+    fn colons_are_punctuators() {
+        let tokenizer = Tokenizer::new("x : float\n:int names");
+        let kinds = tokenizer.map(|token| token.kind).collect::<Vec<TokenKind>>();
 
         assert_eq!(
-            tokens[0].kind,
-            TokenKind::Identifier(Identifier::new("x", Type::new("int"))),
+            kinds,
+            vec![
+                TokenKind::Identifier(Identifier::new("x")),
+                TokenKind::Punctuator(Punctuator::new(":")),
+                TokenKind::Type(Type::new("float")),
+                TokenKind::Punctuator(Punctuator::new(":")),
+                TokenKind::Type(Type::new("int")),
+                TokenKind::Identifier(Identifier::new("names")),
+                TokenKind::Eof,
+            ]
         );
     }
 
     #[test]
     fn operator() {
-        let mut tokenizer = Tokenizer::new("+");
-        let mut tokens = Vec::new();
+        let tokenizer = Tokenizer::new("+");
+        let tokens = tokenizer.collect::<Vec<Token>>();
+
+        assert_eq!(tokens[0].kind, TokenKind::Operator(Operator::new("+")));
+    }
+
+    #[test]
+    fn punctuator() {
+        let tokenizer = Tokenizer::new(";");
+        let tokens = tokenizer.collect::<Vec<Token>>();
+
+        assert_eq!(tokens[0].kind, TokenKind::Punctuator(Punctuator::new(";")));
+    }
+
+    #[test]
+    fn type_names() {
+        let tokenizer = Tokenizer::new("array<int?> Point");
+        let kinds = tokenizer.map(|token| token.kind).collect::<Vec<TokenKind>>();
+
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::Type(Type::Array(None)),
+                TokenKind::Operator(Operator::new("<")),
+                TokenKind::Type(Type::Int),
+                TokenKind::Punctuator(Punctuator::new("?")),
+                TokenKind::Operator(Operator::new(">")),
+                TokenKind::Identifier(Identifier::new("Point")),
+                TokenKind::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn type_display() {
+        let func = Type::Func(vec![Type::Int, Type::Int], Some(Box::new(Type::Bool)));
+
+        assert_eq!(func.to_string(), "func(int, int): bool");
+        assert_eq!(Type::Optional(Box::new(func)).to_string(), "(func(int, int): bool)?");
+        assert_eq!(
+            Type::Map(Box::new(Type::String), Box::new(Type::Array(Some(Box::new(Type::Float))))).to_string(),
+            "map<string, array<float>>"
+        );
+        assert_eq!(Type::Tuple(vec![Type::Int]).to_string(), "(int,)");
+        assert_eq!(Type::Tuple(vec![]).to_string(), "()");
+        assert_eq!(Type::new("Point").to_string(), "Point");
+    }
+
+    #[test]
+    fn punctuators_are_single_chars() {
+        let tokens = Tokenizer::new("();").collect::<Vec<Token>>();
+
+        assert_eq!(tokens[0].kind, TokenKind::Punctuator(Punctuator::new("(")));
+        assert_eq!(tokens[1].kind, TokenKind::Punctuator(Punctuator::new(")")));
+        assert_eq!(tokens[2].kind, TokenKind::Punctuator(Punctuator::new(";")));
+    }
+
+    #[test]
+    fn two_char_punctuators() {
+        let tokens = Tokenizer::new("Shape::Circle(r) => r == ::").collect::<Vec<Token>>();
+
+        assert_eq!(
+            tokens[1],
+            Token::new(TokenKind::Punctuator(Punctuator::new("::")), 1, 6, 8).with_bytes(5, 7)
+        );
+        assert_eq!(
+            tokens[6],
+            Token::new(TokenKind::Punctuator(Punctuator::new("=>")), 1, 18, 20).with_bytes(17, 19)
+        );
+        assert_eq!(tokens[8].kind, TokenKind::Operator(Operator::new("==")));
+        assert_eq!(tokens[9].kind, TokenKind::Punctuator(Punctuator::new("::")));
+    }
+
+    #[test]
+    fn longest_operator_match() {
+        let operators = |input: &str| {
+            Tokenizer::new(input)
+                .filter_map(|token| match token.kind {
+                    TokenKind::Operator(operator) => Some(operator.0),
+                    _ => None,
+                })
+                .collect::<Vec<String>>()
+        };
+
+        assert_eq!(operators("x+-y"), ["+", "-"]);
+        assert_eq!(operators("a!=-1"), ["!=", "-"]);
+        assert_eq!(operators("a<=b>=c<d>e"), ["<=", ">=", "<", ">"]);
+        assert_eq!(operators("i+++j"), ["++", "+"]);
+        assert_eq!(operators("!!done"), ["!", "!"]);
+        assert_eq!(operators("a--->b"), ["--", "-", ">"]);
+    }
+
+    #[test]
+    fn unknown_operator() {
+        let tokens = Tokenizer::new("a & b | c").collect::<Vec<Token>>();
+
+        assert_eq!(
+            tokens[1],
+            Token::new(
+                TokenKind::Error(Error::new(ErrorType::InvalidToken, "Invalid token", "&")),
+                1,
+                3,
+                4
+            ).with_bytes(2, 3)
+        );
+        assert_eq!(
+            tokens[3].kind,
+            TokenKind::Error(Error::new(ErrorType::InvalidToken, "Invalid token", "|"))
+        );
+        assert_eq!(tokens[4].kind, TokenKind::Identifier(Identifier::new("c")));
+    }
+
+    #[test]
+    fn paired_operators() {
+        let tokens = Tokenizer::new("x == 1 = 2").collect::<Vec<Token>>();
+
+        assert_eq!(tokens[1].kind, TokenKind::Operator(Operator::new("==")));
+        assert_eq!(tokens[1].position, Position::new(1, 3, 5).with_bytes(2, 4));
+        assert_eq!(tokens[3].kind, TokenKind::Punctuator(Punctuator::new("=")));
+
+        let tokens = Tokenizer::new("a&&b||c").collect::<Vec<Token>>();
+
+        assert_eq!(tokens[1].kind, TokenKind::Operator(Operator::new("&&")));
+        assert_eq!(tokens[3].kind, TokenKind::Operator(Operator::new("||")));
+    }
+
+    #[test]
+    fn next_line() {
+        let tokenizer = Tokenizer::new("\n \"hello world\"");
+        let tokens = tokenizer.collect::<Vec<Token>>();
+
+        //position should be line 2
+        assert_eq!(tokens[0].position.line, 2);
+    }
+
+    #[test]
+    fn array(){
+        let tokenizer = Tokenizer::new("[1, 2, 3.4]");
+        let tokens = tokenizer.collect::<Vec<Token>>();
+    
+        let expected = [
+            Token::new(TokenKind::Punctuator(Punctuator("[".to_string())), 1, 1, 2).with_bytes(0, 1),
+            Token::new(TokenKind::IntLiteral(IntLiteral(1)), 1, 2, 3).with_bytes(1, 2),
+            Token::new(TokenKind::Punctuator(Punctuator(",".to_string())), 1, 3, 4).with_bytes(2, 3),
+            Token::new(TokenKind::IntLiteral(IntLiteral(2)), 1, 5, 6).with_bytes(4, 5),
+            Token::new(TokenKind::Punctuator(Punctuator(",".to_string())), 1, 6, 7).with_bytes(5, 6),
+            Token::new(TokenKind::FloatLiteral(FloatLiteral(3.4)), 1, 8, 11).with_bytes(7, 10),
+            Token::new(TokenKind::Punctuator(Punctuator("]".to_string())), 1, 11, 12).with_bytes(10, 11),
+        ];
+
+        assert_eq!(tokens[..7], expected);
+        assert_eq!(tokens[7].kind, TokenKind::Eof);
+        
+    }
+
+    #[test]
+    fn multi_line_array() {
+        let tokens = Tokenizer::new("[\n  1,\n  2\n]").collect::<Vec<Token>>();
+
+        assert_eq!(tokens[0].kind, TokenKind::Punctuator(Punctuator::new("[")));
+        assert_eq!(tokens[1].position, Position::new(2, 3, 4).with_bytes(4, 5));
+        assert_eq!(tokens[3].position, Position::new(3, 3, 4).with_bytes(9, 10));
+        assert_eq!(tokens[4], Token::new(TokenKind::Punctuator(Punctuator::new("]")), 4, 1, 2).with_bytes(11, 12));
+    }
+
+    #[test]
+    fn multi_byte() {
+        let tokenizer = Tokenizer::new("let café = \"héllo wörld\";");
+        let tokens = tokenizer.collect::<Vec<Token>>();
+
+        assert_eq!(
+            tokens[1].kind,
+            TokenKind::Identifier(Identifier::new("café"))
+        );
+        assert_eq!(tokens[1].position, Position::new(1, 5, 9).with_bytes(4, 9));
+        assert_eq!(
+            tokens[3].kind,
+            TokenKind::StringLiteral(StringLiteral::new("héllo wörld"))
+        );
+        assert_eq!(tokens[4].kind, TokenKind::Punctuator(Punctuator::new(";")));
+    }
+
+    #[test]
+    fn identifiers() {
+        let source = "x1 user_id _tmp _ __init__ élan naïve Ärger 名前 变量2 λ";
+        let tokens = Tokenizer::new(source)
+            .map(|token| token.kind)
+            .collect::<Vec<TokenKind>>();
+
+        let expected = source
+            .split(' ')
+            .map(|name| TokenKind::Identifier(Identifier::new(name)))
+            .chain([TokenKind::Eof])
+            .collect::<Vec<TokenKind>>();
+
+        assert_eq!(tokens, expected);
+    }
+
+    #[test]
+    fn identifier_boundaries() {
+        let tokenizer = Tokenizer::new("名前+x\u{301}y 1x 😀");
+        let tokens = tokenizer.collect::<Vec<Token>>();
+
+        assert_eq!(
+            tokens[0],
+            Token::new(TokenKind::Identifier(Identifier::new("名前")), 1, 1, 3).with_bytes(0, 6)
+        );
+        assert_eq!(tokens[1].kind, TokenKind::Operator(Operator::new("+")));
+        //a combining mark can continue an identifier but not start one
+        assert_eq!(
+            tokens[2],
+            Token::new(TokenKind::Identifier(Identifier::new("x\u{301}y")), 1, 4, 7).with_bytes(7, 11)
+        );
+        assert_eq!(
+            tokens[3].kind,
+            TokenKind::Error(Error::new(ErrorType::MalformedNumber, "Malformed number", "1x"))
+        );
+        assert_eq!(
+            tokens[4].kind,
+            TokenKind::Error(Error::new(ErrorType::InvalidToken, "Invalid token", "😀"))
+        );
+    }
+
+    #[test]
+    fn trailing_whitespace() {
+        let tokenizer = Tokenizer::new("10 \n ");
+        let tokens = tokenizer.collect::<Vec<Token>>();
+
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(tokens[1].kind, TokenKind::Eof);
+    }
+
+    #[test]
+    fn invalid_token() {
+        let tokenizer = Tokenizer::new("# 1");
+        let tokens = tokenizer.collect::<Vec<Token>>();
+
+        assert_eq!(
+            tokens[0].kind,
+            TokenKind::Error(Error::new(ErrorType::InvalidToken, "Invalid token", "#"))
+        );
+        assert_eq!(tokens[1].kind, TokenKind::IntLiteral(IntLiteral(1)));
+    }
+
+    #[test]
+    fn division_is_not_a_comment() {
+        let tokenizer = Tokenizer::new("10 / 2 // comment");
+        let tokens = tokenizer.collect::<Vec<Token>>();
+
+        assert_eq!(tokens.len(), 4);
+        assert_eq!(tokens[1].kind, TokenKind::Operator(Operator::new("/")));
+    }
+
+    #[test]
+    fn block_comment() {
+        let tokenizer = Tokenizer::new("let /* one\n  two */ x /**/ = /* a /* b */ c */ 1;");
+        let tokens = tokenizer.collect::<Vec<Token>>();
+
+        assert_eq!(tokens.len(), 6);
+        assert_eq!(
+            tokens[1],
+            Token::new(TokenKind::Identifier(Identifier::new("x")), 2, 10, 11).with_bytes(20, 21)
+        );
+        assert_eq!(
+            tokens[3],
+            Token::new(TokenKind::IntLiteral(IntLiteral(1)), 2, 37, 38).with_bytes(47, 48)
+        );
+    }
+
+    #[test]
+    fn unterminated_block_comment() {
+        let tokenizer = Tokenizer::new("1\n  /* a /* b */\n c");
+        let tokens = tokenizer.collect::<Vec<Token>>();
+
+        assert_eq!(
+            tokens[1],
+            Token::new(
+                TokenKind::Error(Error::new(
+                    ErrorType::UnterminatedComment,
+                    "Unterminated block comment",
+                    "/*"
+                )),
+                2,
+                3,
+                15,
+            ).with_bytes(4, 19)
+        );
+        assert_eq!(tokens[2], Token::new(TokenKind::Eof, 3, 3, 3).with_bytes(19, 19));
+    }
+
+    #[test]
+    fn doc_comment() {
+        let tokenizer = Tokenizer::new("/// Adds one\n//// not docs\nfunc");
+        let tokens = tokenizer.collect::<Vec<Token>>();
+
+        assert_eq!(
+            tokens[0],
+            Token::new(TokenKind::DocComment(" Adds one".to_string()), 1, 1, 13).with_bytes(0, 12)
+        );
+        assert_eq!(tokens[1].kind, TokenKind::Keyword(Keyword::new("func")));
+        assert_eq!(tokens.len(), 3);
+    }
+
+    #[test]
+    fn eof() {
+        let mut tokenizer = Tokenizer::new("let\n  x");
+
+        assert_eq!(tokenizer.next().unwrap().kind, TokenKind::Keyword(Keyword::new("let")));
+        assert!(tokenizer.next().is_some());
+        assert_eq!(tokenizer.next(), Some(Token::new(TokenKind::Eof, 2, 4, 4).with_bytes(7, 7)));
+        assert_eq!(tokenizer.next(), None);
+        assert_eq!(tokenizer.next(), None);
+    }
+
+    #[test]
+    fn error_accessors() {
+        let error = Error::new(ErrorType::InvalidToken, "Invalid token", "#");
+
+        assert_eq!(error.error_type(), &ErrorType::InvalidToken);
+        assert_eq!(error.message(), "Invalid token");
+        assert_eq!(error.errorstring(), "#");
+        assert_eq!(error.to_string(), "Invalid token: #");
+        assert_eq!(error.error_type().code(), "E0002");
+    }
+
+    #[test]
+    fn tokenize_all() {
+        let tokens = tokenize("let x: int = 1;").unwrap();
+
+        assert_eq!(tokens.len(), 8);
+        assert_eq!(tokens[7].kind, TokenKind::Eof);
+
+        let errors = tokenize("let # = [1, $];").unwrap_err();
+
+        assert_eq!(
+            errors,
+            vec![
+                Error::new(ErrorType::InvalidToken, "Invalid token", "#"),
+                Error::new(ErrorType::InvalidToken, "Invalid token", "$"),
+            ]
+        );
+    }
+
+    //check that every token of source covers exactly its own text: the spans are in order,
+    //slicing one out and tokenizing it on its own gives the same token, only whitespace and
+    //comments are left between them and the columns agree with the source map
+    fn check_spans(source: &str) {
+        let mut sources = SourceMap::new();
+        let file = sources.add("main.bs", source);
+        let tokens = Tokenizer::new(source).collect::<Vec<Token>>();
+        let mut end = 0;
+
+        for token in &tokens {
+            let span = token.position.span;
+            let text = &source[span.range()];
+
+            let gap = &source[end..span.start_byte];
+            let gap_tokens = Tokenizer::new(gap).collect::<Vec<Token>>();
+            assert_eq!(gap_tokens.len(), 1, "{:?} between tokens of {:?}", gap, source);
+
+            //template parts, and the error for one that is never closed, depend on the
+            //tokens around them so they can not be read alone
+            let template = matches!(
+                token.kind,
+                TokenKind::TemplateHead(_) | TokenKind::TemplateMiddle(_) | TokenKind::TemplateTail(_)
+            ) || matches!(token.kind, TokenKind::Error(_)) && text.starts_with('}');
+
+            if token.kind != TokenKind::Eof && !template {
+                assert!(!span.is_empty(), "empty {:?} in {:?}", token, source);
+                let alone = Tokenizer::new(text).next().unwrap();
+                assert_eq!(alone.position.span.len(), span.len(), "{:?} in {:?}", text, source);
+
+                //the position an error points at moves with the token, so only the rest is compared
+                match (&alone.kind, &token.kind) {
+                    (TokenKind::Error(alone), TokenKind::Error(error)) => {
+                        assert_eq!(
+                            (alone.error_type(), alone.message(), alone.errorstring()),
+                            (error.error_type(), error.message(), error.errorstring()),
+                            "{:?} in {:?}",
+                            text,
+                            source
+                        );
+                    }
+                    (alone, kind) => assert_eq!(alone, kind, "{:?} in {:?}", text, source),
+                }
+            }
+
+            //an error pointing inside its token points at text of that token
+            if let TokenKind::Error(error) = &token.kind {
+                if let Some(inner) = error.position() {
+                    assert!(
+                        span.start_byte <= inner.span.start_byte && inner.span.end_byte <= span.end_byte,
+                        "{:?} in {:?}",
+                        token,
+                        source
+                    );
+                }
+            }
+
+            let location = sources.location(file, span.start_byte);
+            assert_eq!(
+                (location.line, location.column),
+                (token.position.line, token.position.start_column),
+                "{:?} in {:?}",
+                token,
+                source
+            );
+
+            let first_line = text.split('\n').next().unwrap();
+            assert_eq!(
+                token.position.end_column - token.position.start_column,
+                first_line.chars().count(),
+                "{:?} in {:?}",
+                token,
+                source
+            );
+
+            end = span.end_byte;
+        }
+
+        assert_eq!(tokens.last().unwrap().kind, TokenKind::Eof);
+        assert_eq!(end, source.len());
+
+        //a lossless tokenizer reads the same tokens, and with their trivia they give back the input
+        let lossless = Tokenizer::new(source).lossless().collect::<Vec<Token>>();
+        let mut text = String::new();
+
+        for (token, plain) in lossless.iter().zip(&tokens) {
+            assert_eq!((&token.kind, &token.position), (&plain.kind, &plain.position));
+
+            for trivia in &token.leading_trivia {
+                assert_eq!(&source[trivia.position.span.range()], trivia.text);
+                text += &trivia.text;
+            }
+            text += &source[token.position.span.range()];
+            for trivia in &token.trailing_trivia {
+                assert_eq!(&source[trivia.position.span.range()], trivia.text);
+                text += &trivia.text;
+            }
+        }
+
+        assert_eq!(lossless.len(), tokens.len());
+        assert_eq!(text, source);
+    }
+
+    #[test]
+    fn spans_cover_the_source() {
+        for source in [
+            "let x: int = 10 + 20.1;\nlet s: string = \"hello world\";",
+            "x:int",
+            "\"a\\qb\\zc\" + \"${a + \"${b}\"} and ${ {c} }!\"",
+            "let s = \"abc",
+            "let s = \"a\nb\";\n\"${x",
+            "/// doc\n//// plain\nlet é = \"𝄞\"; // end",
+            "1\n  /* a /* b */ c */ 2 /* open",
+            "12abc 0x1G 99999999999999999999 1.5e3 1.",
+            "a::b => c != -1 # @ \r\n\tz",
+            "",
+        ] {
+            check_spans(source);
+        }
+    }
+
+    #[test]
+    fn trivia() {
+        let source = "let x = 1; // one\n\n  /* two */ x /// doc\n";
+        let tokens = Tokenizer::new(source).lossless().collect::<Vec<Token>>();
+
+        let trivia = |trivia: &[Trivia]| {
+            trivia
+                .iter()
+                .map(|trivia| (trivia.kind, trivia.text.clone()))
+                .collect::<Vec<(TriviaKind, String)>>()
+        };
+
+        assert_eq!(
+            trivia(&tokens[0].trailing_trivia),
+            [(TriviaKind::Whitespace, " ".to_string())]
+        );
+        assert_eq!(
+            trivia(&tokens[4].trailing_trivia),
+            [
+                (TriviaKind::Whitespace, " ".to_string()),
+                (TriviaKind::LineComment, "// one".to_string()),
+            ]
+        );
+        assert_eq!(
+            trivia(&tokens[5].leading_trivia),
+            [
+                (TriviaKind::Newline, "\n".to_string()),
+                (TriviaKind::Newline, "\n".to_string()),
+                (TriviaKind::Whitespace, "  ".to_string()),
+                (TriviaKind::BlockComment, "/* two */".to_string()),
+                (TriviaKind::Whitespace, " ".to_string()),
+            ]
+        );
+        assert_eq!(tokens[5].position, Position::new(3, 13, 14).with_bytes(31, 32));
+        assert_eq!(tokens[6].kind, TokenKind::DocComment(" doc".to_string()));
+        assert_eq!(
+            trivia(&tokens[7].leading_trivia),
+            [(TriviaKind::Newline, "\n".to_string())]
+        );
+        assert_eq!(
+            tokens[5].leading_trivia[3].position,
+            Position::new(3, 3, 12).with_bytes(21, 30)
+        );
 
-        while let Some(token) = tokenizer.next() {
-            tokens.push(token);
-        }
+        //without lossless the trivia is dropped
+        let tokens = Tokenizer::new(source).collect::<Vec<Token>>();
 
-        assert_eq!(tokens[0].kind, TokenKind::Operator(Operator::new("+")));
+        assert!(tokens
+            .iter()
+            .all(|token| token.leading_trivia.is_empty() && token.trailing_trivia.is_empty()));
     }
 
-    #[test]
-    fn punctuator() {
-        let mut tokenizer = Tokenizer::new(";");
-        let mut tokens = Vec::new();
+    //a xorshift generator, so the random inputs are the same on every run
+    struct Random(u64);
 
-        while let Some(token) = tokenizer.next() {
-            tokens.push(token);
+    impl Random {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
         }
 
-        assert_eq!(tokens[0].kind, TokenKind::Punctuator(Punctuator::new(";")));
-    }
-
-    #[test]
-    fn next_line() {
-        let mut tokenizer = Tokenizer::new("\n \"hello world\"");
-        let mut tokens = Vec::new();
-
-        while let Some(token) = tokenizer.next() {
-            tokens.push(token);
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
         }
-
-        //position should be line 2
-        assert_eq!(tokens[0].position.line, 2);
     }
 
     #[test]
-    fn array(){
-        let mut tokenizer = Tokenizer::new("[1, 2, 3.4]");
-        let mut tokens = Vec::new();
-
-        while let Some(token) = tokenizer.next() {
-            tokens.push(token);
+    fn spans_cover_random_sources() {
+        //pieces that start and end every kind of token, glued together at random so
+        //they also split each other up
+        let pieces = [
+            "let", "x", "é", "_a1", "𝄞", "int", "true", " ", "  ", "\n", "\t", "\r\n", "1", "0x1F",
+            "0b2", "1.5", "e3", ".", "_", "\"", "\"s\"", "\\", "\\q", "\\u{41}", "${", "}", "{",
+            "(", ")", "//", "///", "/*", "*/", "+", "++", "=", "==", "!", ":", "::", "=>", ";", ",",
+            "#", "$", "@",
+        ];
+        let mut random = Random(0x2545_f491_4f6c_dd1d);
+
+        for _ in 0..2000 {
+            let length = random.below(24);
+            let source = (0..length)
+                .map(|_| pieces[random.below(pieces.len())])
+                .collect::<String>();
+
+            check_spans(&source);
         }
-    
-        let expected = [Token::new(
-            TokenKind::ArrayLiteral(
-                ArrayLiteral::new(vec![
-                    Token::new(TokenKind::IntLiteral(IntLiteral(1)), 1, 2, 3),
-                    Token::new(TokenKind::Punctuator(Punctuator(",".to_string())), 1, 3, 4),
-                    Token::new(TokenKind::IntLiteral(IntLiteral(2)), 1, 5, 6),
-                    Token::new(TokenKind::Punctuator(Punctuator(",".to_string())), 1, 6, 7),
-                    Token::new(TokenKind::FloatLiteral(FloatLiteral(3.4)), 1, 8, 11),
-                ])
-            ),
-            1,
-            1,
-            12,
-        )];
-
-        assert_eq!(tokens, expected);
-        
     }
 }
 
@@ -255,10 +997,15 @@ const OPERATORS: [&str; 16] = [
 ];
 
 //create a list of all punctuators
-const PUNCTUATORS: [&str; 10] = ["(", ")", "{", "}", "[", "]", ",", ";", ".", "="];
+const PUNCTUATORS: [&str; 14] = [
+    "(", ")", "{", "}", "[", "]", ",", ";", ".", "=", ":", "?", "::", "=>",
+];
 
 //create list of all keywords
-const KEYWORDS: [&str; 3] = ["let", "const", "func"];
+const KEYWORDS: [&str; 15] = [
+    "let", "const", "func", "return", "if", "else", "while", "for", "in", "break", "continue",
+    "struct", "impl", "enum", "match",
+];
 
 const TYPES: [&str; 5] = ["float", "int", "string", "bool", "array"];
 
@@ -280,15 +1027,6 @@ impl BoolLiteral {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
-pub struct ArrayLiteral(pub Vec<Token>);
-
-impl ArrayLiteral {
-    pub fn new(value: Vec<Token>) -> Self {
-        ArrayLiteral(value)
-    }
-}
-
 #[derive(Debug, Clone, PartialEq)]
 pub struct Punctuator(pub String);
 
@@ -301,21 +1039,9 @@ impl Punctuator {
 #[derive(Debug, Clone, PartialEq)]
 pub struct FloatLiteral(pub f64);
 
-impl FloatLiteral {
-    pub fn new(value: &str) -> Self {
-        FloatLiteral(value.parse::<f64>().unwrap())
-    }
-}
-
 #[derive(Debug, Clone, PartialEq)]
 pub struct IntLiteral(pub i64);
 
-impl IntLiteral {
-    pub fn new(value: &str) -> Self {
-        IntLiteral(value.parse::<i64>().unwrap())
-    }
-}
-
 #[derive(Debug, Clone, PartialEq)]
 pub struct StringLiteral(pub String);
 
@@ -325,21 +1051,75 @@ impl StringLiteral {
     }
 }
 
+//a type annotation as written, the lexer only produces the builtin names in TYPES and
+//the parser builds the composite types from those
 #[derive(Debug, Clone, PartialEq)]
-pub struct Type(pub String);
+pub enum Type {
+    Int,
+    Float,
+    String,
+    Bool,
+    //a plain `array` leaves the element type open
+    Array(Option<Box<Type>>),
+    Map(Box<Type>, Box<Type>),
+    Optional(Box<Type>),
+    //parameter types and the return type, None when the function returns nothing
+    Func(Vec<Type>, Option<Box<Type>>),
+    Tuple(Vec<Type>),
+    Named(String),
+}
 
 impl Type {
     pub fn new(value: &str) -> Self {
-        Type(value.to_string())
+        match value {
+            "int" => Type::Int,
+            "float" => Type::Float,
+            "string" => Type::String,
+            "bool" => Type::Bool,
+            "array" => Type::Array(None),
+            name => Type::Named(name.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Type::Int => write!(f, "int"),
+            Type::Float => write!(f, "float"),
+            Type::String => write!(f, "string"),
+            Type::Bool => write!(f, "bool"),
+            Type::Array(None) => write!(f, "array"),
+            Type::Array(Some(element)) => write!(f, "array<{}>", element),
+            Type::Map(key, value) => write!(f, "map<{}, {}>", key, value),
+            //without the parens the ? would belong to the return type
+            Type::Optional(inner) if matches!(**inner, Type::Func(..)) => write!(f, "({})?", inner),
+            Type::Optional(inner) => write!(f, "{}?", inner),
+            Type::Func(params, ret) => {
+                write!(f, "func({})", join(params))?;
+
+                match ret {
+                    Some(ret) => write!(f, ": {}", ret),
+                    None => Ok(()),
+                }
+            }
+            Type::Tuple(elements) if elements.len() == 1 => write!(f, "({},)", elements[0]),
+            Type::Tuple(elements) => write!(f, "({})", join(elements)),
+            Type::Named(name) => write!(f, "{}", name),
+        }
     }
 }
 
+fn join(types: &[Type]) -> String {
+    types.iter().map(Type::to_string).collect::<Vec<String>>().join(", ")
+}
+
 #[derive(Debug, Clone, PartialEq)]
-pub struct Identifier(pub String, pub Type);
+pub struct Identifier(pub String);
 
 impl Identifier {
-    pub fn new(value: &str, type_: Type) -> Self {
-        Identifier(value.to_string(), type_)
+    pub fn new(value: &str) -> Self {
+        Identifier(value.to_string())
     }
 }
 
@@ -356,6 +1136,26 @@ impl Keyword {
 pub struct Token {
     pub kind: TokenKind,
     pub position: Position,
+    //only filled in by a lossless tokenizer, trailing trivia is everything after the token
+    //up to the end of its line and leading trivia is the rest of what comes before it
+    pub leading_trivia: Vec<Trivia>,
+    pub trailing_trivia: Vec<Trivia>,
+}
+
+//the text between tokens, which the parser never sees
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TriviaKind {
+    Whitespace,
+    Newline,
+    LineComment,
+    BlockComment,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub text: String,
+    pub position: Position,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -363,6 +1163,8 @@ pub struct Position {
     pub line: usize,
     pub start_column: usize,
     pub end_column: usize,
+    //the bytes of the source this covers, unlike the columns it can run over several lines
+    pub span: Span,
 }
 
 impl Position {
@@ -371,6 +1173,31 @@ impl Position {
             line,
             start_column,
             end_column,
+            span: Span::default(),
+        }
+    }
+
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
+    }
+
+    //set the byte range of the span, keeping its file
+    pub fn with_bytes(mut self, start_byte: usize, end_byte: usize) -> Self {
+        self.span = Span::new(self.span.file, start_byte, end_byte);
+        self
+    }
+
+    //the position from the start of self to the end of other
+    //a position only covers one line, so if other ends on a later line the end is cut off at self
+    //the span is always joined, so it still covers both
+    pub fn to(&self, other: &Position) -> Position {
+        let span = self.span.to(&other.span);
+
+        if other.line == self.line {
+            Position::new(self.line, self.start_column, other.end_column).with_span(span)
+        } else {
+            self.clone().with_span(span)
         }
     }
 }
@@ -380,8 +1207,20 @@ impl Token {
         Token {
             kind,
             position: Position::new(line, start_column, end_column),
+            leading_trivia: Vec::new(),
+            trailing_trivia: Vec::new(),
         }
     }
+
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.position.span = span;
+        self
+    }
+
+    pub fn with_bytes(mut self, start_byte: usize, end_byte: usize) -> Self {
+        self.position = self.position.with_bytes(start_byte, end_byte);
+        self
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -389,386 +1228,646 @@ pub enum TokenKind {
     Operator(Operator),
     Punctuator(Punctuator),
     BoolLiteral(BoolLiteral),
-    ArrayLiteral(ArrayLiteral),
     FloatLiteral(FloatLiteral),
     IntLiteral(IntLiteral),
     StringLiteral(StringLiteral),
+    //an interpolated string is split around its ${expr} parts, "a ${x} b ${y} c" becomes
+    //TemplateHead("a ") x TemplateMiddle(" b ") y TemplateTail(" c")
+    TemplateHead(StringLiteral),
+    TemplateMiddle(StringLiteral),
+    TemplateTail(StringLiteral),
     Identifier(Identifier),
     Type(Type),
     Keyword(Keyword),
+    //the text after /// up to the end of the line, ordinary comments produce no token
+    DocComment(String),
     Error(Error),
+    Eof,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Tokenizer {
     input: String,
+    //the file every span is in
+    file: FileId,
+    //byte offset of the cursor into input, always on a char boundary
     position: usize,
     line: usize,
     column: usize,
+    //one entry per ${ that is still open, counting the { } pairs inside it so the
+    //} that closes the interpolation can be told apart from the end of a block
+    interpolations: Vec<usize>,
+    //set once the eof token has been handed out
+    finished: bool,
+    //set by lossless, the trivia read since the last token is kept until the next one
+    keep_trivia: bool,
+    trivia: Vec<Trivia>,
+}
+
+impl Iterator for Tokenizer {
+    type Item = Token;
+
+    //yields every token followed by a single eof token, then only None
+    fn next(&mut self) -> Option<Token> {
+        if self.finished {
+            return None;
+        }
+
+        let token = self.next_token();
+
+        if token.is_none() {
+            self.finished = true;
+
+            let mut eof = self.token(TokenKind::Eof, self.position, self.line, self.column);
+            eof.leading_trivia = std::mem::take(&mut self.trivia);
+            return Some(eof);
+        }
+
+        token
+    }
+}
+
+impl FusedIterator for Tokenizer {}
+
+//tokenize the whole input, splitting off the error tokens
+pub fn tokenize(input: &str) -> Result<Vec<Token>, Vec<Error>> {
+    let tokens = Tokenizer::new(input).collect::<Vec<Token>>();
+
+    let errors = tokens
+        .iter()
+        .filter_map(|token| match &token.kind {
+            TokenKind::Error(error) => Some(error.clone()),
+            _ => None,
+        })
+        .collect::<Vec<Error>>();
+
+    if errors.is_empty() {
+        Ok(tokens)
+    } else {
+        Err(errors)
+    }
 }
 
 impl Tokenizer {
     pub fn new(input: &str) -> Self {
+        Tokenizer::with_file(input, FileId::default())
+    }
+
+    //tokenize input as the file with the given id, for sources in a SourceMap
+    pub fn with_file(input: &str, file: FileId) -> Self {
         Tokenizer {
             input: input.to_string(),
+            file,
             position: 0,
             line: 1,
             column: 1,
+            interpolations: Vec::new(),
+            finished: false,
+            keep_trivia: false,
+            trivia: Vec::new(),
         }
     }
 
-    pub fn next(&mut self) -> Option<Token> {
-        //start column is the current column
-        let start_column = self.column;
-
-        if self.position >= self.input.len() {
-            return None;
-        }
-
-        let mut token = None;
-
-        while token.is_none() {
-            let c = self.input.chars().nth(self.position).unwrap();
+    //keep whitespace and comments as trivia on the tokens, so the text of the tokens and
+    //their trivia put back together is exactly the input
+    pub fn lossless(mut self) -> Self {
+        self.keep_trivia = true;
+        self
+    }
 
-            if c == '\n' {
-                self.line += 1;
-                self.column = 1;
-                self.position += 1;
-            } else if c.is_whitespace() {
-                self.skip_whitespace();
-            } else if c.is_numeric() {
-                token = Some(self.read_number());
+    //read the next token, returns None once the input is used up
+    fn next_token(&mut self) -> Option<Token> {
+        loop {
+            //where the token starts, its position is only worked out once it has been read
+            let (start, line, column) = (self.position, self.line, self.column);
+            let c = self.peek()?;
+
+            let kind = if let Some(trivia) = self.read_trivia() {
+                Err(trivia)
+            } else if c.is_ascii_digit() {
+                Ok(self.read_number())
             } else if c == '"' {
-                token = Some(self.read_string());
-            } else if c.is_alphabetic() {
-                token = Some(self.read_identifier());
-            } else if c == '/' && self.peek() == Some('/') {
-                self.skip_comment();
-            } else if OPERATORS.contains(&c.to_string().as_str()) {
-                token = Some(self.read_operator());
-            } else if PUNCTUATORS.contains(&c.to_string().as_str()) {
-                token = Some(self.read_punctuator());
+                Ok(self.read_string())
+            } else if c == '}' && self.interpolations.last() == Some(&0) {
+                Ok(self.read_template_continuation())
+            } else if xid::is_xid_start(c) || c == '_' {
+                Ok(self.read_identifier())
+            } else if c == '/' && self.peek_next() == Some('/') {
+                Ok(self.read_doc_comment())
+            } else if c == '/' && self.peek_next() == Some('*') {
+                //read_trivia only takes block comments that are closed
+                Ok(self.unterminated_comment())
+            } else if let Some(operator) = self.match_operator() {
+                Ok(self.read_operator(operator))
+            } else if let Some(punctuator) = self.match_punctuator() {
+                Ok(self.read_punctuator(punctuator))
             }
             //check for a new
             else {
+                //consume the char so the next call does not see it again
+                self.bump();
+
                 //create an error with an error type
-                token = Some(Token::new(
-                    TokenKind::Error(Error::new(
-                        ErrorType::InvalidToken,
-                        "Invalid token",
-                        &c.to_string().as_str(),
-                    )),
-                    self.line,
-                    start_column,
-                    self.column,
-                ));
+                Ok(TokenKind::Error(Error::new(
+                    ErrorType::InvalidToken,
+                    "Invalid token",
+                    c.to_string().as_str(),
+                )))
+            };
+
+            match kind {
+                Ok(kind) => {
+                    let mut token = self.token(kind, start, line, column);
+
+                    if self.keep_trivia {
+                        token.leading_trivia = std::mem::take(&mut self.trivia);
+                        token.trailing_trivia = self.read_trailing_trivia();
+                    }
+
+                    return Some(token);
+                }
+                Err(kind) if self.keep_trivia => {
+                    let trivia = self.trivia(kind, start, line, column);
+                    self.trivia.push(trivia);
+                }
+                Err(_) => {}
             }
         }
-        token
     }
 
-    //write a peek function that return the next char
-    pub fn peek(&mut self) -> Option<char> {
-        if self.position >= self.input.len() {
-            return None;
-        }
+    //read one piece of whitespace or a comment at the cursor, a newline is always a piece
+    //of its own; doc comments and block comments that are never closed are left alone
+    //because they become tokens
+    fn read_trivia(&mut self) -> Option<TriviaKind> {
+        let rest = &self.input[self.position..];
+        let line_comment =
+            rest.starts_with("//") && (!rest.starts_with("///") || rest.starts_with("////"));
+        let block_comment = rest.starts_with("/*").then(|| block_comment_length(rest)).flatten();
+
+        match self.peek()? {
+            '\n' => {
+                self.bump();
+                Some(TriviaKind::Newline)
+            }
+            c if c.is_whitespace() => {
+                self.skip_whitespace();
+                Some(TriviaKind::Whitespace)
+            }
+            _ if line_comment => {
+                self.skip_comment();
+                Some(TriviaKind::LineComment)
+            }
+            _ => {
+                let end = self.position + block_comment?;
 
-        let c = self.input.chars().nth(self.position).unwrap();
-        Some(c)
+                while self.position < end {
+                    self.bump();
+                }
+                Some(TriviaKind::BlockComment)
+            }
+        }
     }
 
-    fn skip_whitespace(&mut self) {
-        let c = self.input.chars().nth(self.position).unwrap();
+    //the trivia after a token up to the end of its line, the newline itself is left for
+    //the next token
+    fn read_trailing_trivia(&mut self) -> Vec<Trivia> {
+        let mut trivia = Vec::new();
 
-        if c.is_whitespace() {
-            self.position += 1;
-            self.column += 1;
+        while self.peek().is_some_and(|c| c != '\n') {
+            let (start, line, column) = (self.position, self.line, self.column);
+
+            match self.read_trivia() {
+                Some(kind) => trivia.push(self.trivia(kind, start, line, column)),
+                None => break,
+            }
         }
+
+        trivia
     }
 
-    pub fn skip_comment(&mut self) {
-        self.position += 2;
-        self.column += 2;
+    fn trivia(&self, kind: TriviaKind, start: usize, line: usize, column: usize) -> Trivia {
+        Trivia {
+            kind,
+            text: self.input[start..self.position].to_string(),
+            position: self.token(TokenKind::Eof, start, line, column).position,
+        }
+    }
 
-        while self.position < self.input.len() {
-            let c = self.input.chars().nth(self.position).unwrap();
+    //the token for everything read since start, so its span covers exactly its source text
+    //and its columns run to the cursor, or to the end of its first line if it spans several
+    fn token(&self, kind: TokenKind, start: usize, line: usize, column: usize) -> Token {
+        let text = &self.input[start..self.position];
+        let first_line = text.split('\n').next().unwrap_or_default();
 
-            if c == '\n' {
-                self.position += 1;
-                self.column = 1;
-                self.line += 1;
-                break;
-            } else {
-                self.position += 1;
-                self.column += 1;
-            }
-        }
+        Token::new(kind, line, column, column + first_line.chars().count())
+            .with_span(Span::new(self.file, start, self.position))
     }
 
-    fn read_number(&mut self) -> Token {
-        //read number and check if it is a float or int
-        let mut value = String::new();
+    //return the char at the cursor without consuming it
+    pub fn peek(&self) -> Option<char> {
+        self.input[self.position..].chars().next()
+    }
 
-        while self.position < self.input.len() {
-            let c = self.input.chars().nth(self.position).unwrap();
+    //return the char after the one at the cursor
+    pub fn peek_next(&self) -> Option<char> {
+        let mut chars = self.input[self.position..].chars();
+        chars.next();
+        chars.next()
+    }
 
-            if c.is_numeric() {
-                value.push(c);
-                self.position += 1;
-                self.column += 1;
-            } else if c == '.' {
-                value.push(c);
-                self.position += 1;
-                self.column += 1;
-                break;
-            } else {
-                break;
-            }
+    //consume the char at the cursor, moving the byte offset by its utf-8 length
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+
+        self.position += c.len_utf8();
+
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
         }
 
-        while self.position < self.input.len() {
-            let c = self.input.chars().nth(self.position).unwrap();
+        Some(c)
+    }
 
-            if c.is_numeric() {
-                value.push(c);
-                self.position += 1;
-                self.column += 1;
-            } else {
+    //consume chars while the predicate holds and return them
+    fn bump_while(&mut self, predicate: impl Fn(char) -> bool) -> &str {
+        let start = self.position;
+
+        while let Some(c) = self.peek() {
+            if !predicate(c) {
                 break;
             }
+            self.bump();
         }
 
-        if value.contains('.') {
-            Token::new(
-                TokenKind::FloatLiteral(FloatLiteral::new(value.as_str())),
-                self.line,
-                self.column - value.len(),
-                self.column,
-            )
+        &self.input[start..self.position]
+    }
+
+    //whitespace up to the next newline, which is read on its own
+    fn skip_whitespace(&mut self) {
+        self.bump_while(|c| c.is_whitespace() && c != '\n');
+    }
+
+    //the rest of a line comment, leaving the newline that ends it
+    pub fn skip_comment(&mut self) {
+        self.bump_while(|c| c != '\n');
+    }
+
+    //a /// comment becomes a doc comment token, //// and longer are plain comments and
+    //read as trivia
+    fn read_doc_comment(&mut self) -> TokenKind {
+        let text = self.bump_while(|c| c != '\n')[3..].to_string();
+
+        TokenKind::DocComment(text)
+    }
+
+    //a block comment the input ends in is an error covering the rest of the input
+    fn unterminated_comment(&mut self) -> TokenKind {
+        self.bump_while(|_| true);
+
+        TokenKind::Error(Error::new(
+            ErrorType::UnterminatedComment,
+            "Unterminated block comment",
+            "/*",
+        ))
+    }
+
+    fn read_number(&mut self) -> TokenKind {
+        let start = self.position;
+
+        let radix = match (self.peek(), self.peek_next()) {
+            (Some('0'), Some('x')) => 16,
+            (Some('0'), Some('b')) => 2,
+            (Some('0'), Some('o')) => 8,
+            _ => 10,
+        };
+
+        if radix == 10 {
+            self.read_decimal(start)
         } else {
-            Token::new(
-                TokenKind::IntLiteral(IntLiteral::new(value.as_str())),
-                self.line,
-                self.column - value.len(),
-                self.column,
-            )
+            self.bump();
+            self.bump();
+            self.read_radix(start, radix)
         }
     }
 
-    fn read_string(&mut self) -> Token {
-        //read string and check if string is closed at the end if not create an error
-        let mut value = String::new();
+    //digits with an optional fraction and exponent, a dot only belongs to the number
+    //when a digit follows it so `1.` is an int and a dot and `.5` is a dot and an int
+    fn read_decimal(&mut self, start: usize) -> TokenKind {
+        let mut is_float = false;
 
-        self.position += 1;
-        self.column += 1;
+        self.bump_while(|c| c.is_ascii_digit() || c == '_');
 
-        while self.position < self.input.len() {
-            let c = self.input.chars().nth(self.position).unwrap();
+        if self.peek() == Some('.') && self.peek_next().is_some_and(|c| c.is_ascii_digit()) {
+            self.bump();
+            self.bump_while(|c| c.is_ascii_digit() || c == '_');
+            is_float = true;
+        }
 
-            if c == '"' {
-                self.position += 1;
-                self.column += 1;
-                break;
-            } else {
-                value.push(c);
-                self.position += 1;
-                self.column += 1;
+        if matches!(self.peek(), Some('e' | 'E')) {
+            self.bump();
+
+            if matches!(self.peek(), Some('+' | '-'))
+                && self.peek_next().is_some_and(|c| c.is_ascii_digit())
+            {
+                self.bump();
             }
-        }
 
-        //check last char if it is a " if not create an error
-        if self.input.chars().nth(self.position - 1).unwrap() != '"' {
-            //create an error with an error type
-            return Token::new(
-                TokenKind::Error(Error::new(ErrorType::InvalidToken, "Invalid token", &value)),
-                self.line,
-                self.column,
-                self.column,
-            );
+            self.bump_while(|c| c.is_ascii_digit() || c == '_');
+            is_float = true;
         }
 
-        Token::new(
-            TokenKind::StringLiteral(StringLiteral::new(value.as_str())),
-            self.line,
-            self.column - value.len(),
-            self.column,
-        )
-    }
+        //letters glued onto the number, like 12abc, make the whole literal malformed
+        self.bump_while(|c| c.is_alphanumeric() || c == '_');
 
-    fn read_identifier(&mut self) -> Token {
-        let mut value = String::new();
+        let text = &self.input[start..self.position];
 
-        while self.position < self.input.len() {
-            let c = self.input.chars().nth(self.position).unwrap();
+        if !valid_decimal(text) {
+            return number_error(ErrorType::MalformedNumber, "Malformed number", text);
+        }
 
-            if c.is_alphabetic() {
-                value.push(c);
-                self.position += 1;
-                self.column += 1;
-            } else {
-                break;
+        let digits = text.replace('_', "");
+
+        if is_float {
+            match digits.parse::<f64>() {
+                Ok(value) if value.is_finite() => TokenKind::FloatLiteral(FloatLiteral(value)),
+                _ => number_error(ErrorType::FloatOverflow, "Float literal out of range", text),
+            }
+        } else {
+            match digits.parse::<i64>() {
+                Ok(value) => TokenKind::IntLiteral(IntLiteral(value)),
+                Err(_) => number_error(ErrorType::IntegerOverflow, "Integer literal out of range", text),
             }
         }
+    }
 
-        // Check if the next character is a colon, indicating a type is specified
-        if let Some(':') = self.input.chars().nth(self.position) {
-            self.position += 1;
-            self.column += 1;
+    //the digits after a 0x, 0b or 0o prefix, these are always ints
+    fn read_radix(&mut self, start: usize, radix: u32) -> TokenKind {
+        let digits = self.bump_while(|c| c.is_alphanumeric() || c == '_').to_string();
+        let text = &self.input[start..self.position];
 
-            // Skip over any whitespace characters before the type name
-            while self.position < self.input.len() {
-                let c = self.input.chars().nth(self.position).unwrap();
+        if !valid_digits(&digits, radix) {
+            return number_error(ErrorType::MalformedNumber, "Malformed number", text);
+        }
 
-                if c.is_whitespace() {
-                    self.position += 1;
-                    self.column += 1;
-                } else {
-                    break;
-                }
-            }
+        match i64::from_str_radix(&digits.replace('_', ""), radix) {
+            Ok(value) => TokenKind::IntLiteral(IntLiteral(value)),
+            Err(_) => number_error(ErrorType::IntegerOverflow, "Integer literal out of range", text),
+        }
+    }
+
+    fn read_string(&mut self) -> TokenKind {
+        let start = self.position;
+        self.bump();
+
+        self.read_string_part(start, false)
+    }
 
-            let mut type_name = String::new();
+    //the } that closes an interpolation continues the string it was opened in
+    fn read_template_continuation(&mut self) -> TokenKind {
+        let start = self.position;
+        self.bump();
 
-            while self.position < self.input.len() {
-                let c = self.input.chars().nth(self.position).unwrap();
+        self.read_string_part(start, true)
+    }
 
-                if c.is_alphabetic() {
-                    type_name.push(c);
-                    self.position += 1;
-                    self.column += 1;
-                } else {
-                    break;
+    //read string contents up to the closing quote or the next ${, continued is true when
+    //the part starts at the } of an interpolation instead of at an opening quote
+    fn read_string_part(&mut self, start: usize, continued: bool) -> TokenKind {
+        let mut value = String::new();
+        //the first bad escape is reported, the rest of the string is still consumed
+        let mut error = None;
+
+        let kind = loop {
+            match self.peek() {
+                //the input ran out before the closing quote
+                None => {
+                    if continued {
+                        self.interpolations.pop();
+                    }
+
+                    return TokenKind::Error(Error::new(
+                        ErrorType::UnterminatedString,
+                        "Unterminated string",
+                        &self.input[start..],
+                    ));
+                }
+                Some('"') => {
+                    self.bump();
+
+                    if continued {
+                        self.interpolations.pop();
+                        break TokenKind::TemplateTail(StringLiteral::new(&value));
+                    }
+                    break TokenKind::StringLiteral(StringLiteral::new(&value));
+                }
+                Some('$') if self.peek_next() == Some('{') => {
+                    self.bump();
+                    self.bump();
+
+                    if continued {
+                        break TokenKind::TemplateMiddle(StringLiteral::new(&value));
+                    }
+                    self.interpolations.push(0);
+                    break TokenKind::TemplateHead(StringLiteral::new(&value));
+                }
+                Some('\\') => {
+                    if let Err(invalid) = self.read_escape(&mut value) {
+                        error.get_or_insert(invalid);
+                    }
+                }
+                Some(c) => {
+                    self.bump();
+                    value.push(c);
                 }
             }
+        };
 
-            if TYPES.contains(&type_name.as_str()) {
-                Token::new(
-                    TokenKind::Identifier(Identifier::new(&value, Type::new(&type_name))),
-                    self.line,
-                    self.column - value.len(),
-                    self.column,
-                )
-            } else {
-                //make a &str from value and type_name
-                let error = value.to_string() + ":" + &type_name;
-                Token::new(
-                    TokenKind::Error(Error::new(
-                        ErrorType::MissingType,
-                        "Missing type",
-                        error.as_str(),
-                    )),
-                    self.line,
-                    self.column - type_name.len(),
-                    self.column,
+        match error {
+            Some(error) => TokenKind::Error(error),
+            None => kind,
+        }
+    }
+
+    //read an escape sequence starting at the backslash and push the char it stands for,
+    //an invalid one is returned as the error for the whole string
+    fn read_escape(&mut self, value: &mut String) -> Result<(), Error> {
+        let (start, line, column) = (self.position, self.line, self.column);
+        self.bump();
+
+        let escaped = match self.bump() {
+            Some('n') => Some('\n'),
+            Some('t') => Some('\t'),
+            Some('\\') => Some('\\'),
+            Some('"') => Some('"'),
+            Some('0') => Some('\0'),
+            Some('$') => Some('$'),
+            Some('u') => self.read_unicode_escape(),
+            //leave the end of input to read_string so it reports the unterminated string
+            None => return Ok(()),
+            Some(_) => None,
+        };
+
+        match escaped {
+            Some(c) => {
+                value.push(c);
+                Ok(())
+            }
+            None => {
+                //the token is the whole string, the error points at the escape in it
+                let escape = self.token(TokenKind::Eof, start, line, column).position;
+
+                Err(Error::new(
+                    ErrorType::InvalidEscape,
+                    "Invalid escape sequence",
+                    &self.input[start..self.position],
                 )
+                .with_position(escape))
             }
-        } else if KEYWORDS.contains(&value.as_str()) {
-            Token::new(
-                TokenKind::Keyword(Keyword::new(&value)),
-                self.line,
-                self.column - value.len(),
-                self.column,
-            )
         }
+    }
 
-        else if &value == "true" || &value == "false" {
-            Token::new(
-                TokenKind::BoolLiteral(BoolLiteral::new(&value)),
-                self.line,
-                self.column - value.len(),
-                self.column,
-            )
+    //the {XXXX} part of \u{XXXX}, one to six hex digits naming a unicode scalar value
+    fn read_unicode_escape(&mut self) -> Option<char> {
+        if self.peek() != Some('{') {
+            return None;
         }
-        
-        else {
-            Token::new(
-                TokenKind::Identifier(Identifier::new(&value, Type::new(""))),
-                self.line,
-                self.column - value.len(),
-                self.column,
-            )
+        self.bump();
+
+        let digits = self.bump_while(|c| c.is_ascii_hexdigit()).to_string();
+
+        if self.peek() != Some('}') {
+            return None;
+        }
+        self.bump();
+
+        if digits.is_empty() || digits.len() > 6 {
+            return None;
         }
+
+        u32::from_str_radix(&digits, 16)
+            .ok()
+            .and_then(char::from_u32)
     }
 
-    fn read_operator(&mut self) -> Token {
-        let mut value = String::new();
+    fn read_identifier(&mut self) -> TokenKind {
+        //the first char was already checked to be XID_Start or `_`
+        let value = self.bump_while(xid::is_xid_continue).to_string();
+
+        //type names get their own token, the parser handles the `:` in front of them
+        if TYPES.contains(&value.as_str()) {
+            TokenKind::Type(Type::new(&value))
+        } else if KEYWORDS.contains(&value.as_str()) {
+            TokenKind::Keyword(Keyword::new(&value))
+        } else if value == "true" || value == "false" {
+            TokenKind::BoolLiteral(BoolLiteral::new(&value))
+        } else {
+            TokenKind::Identifier(Identifier::new(&value))
+        }
+    }
 
-        while self.position < self.input.len() {
-            let c = self.input.chars().nth(self.position).unwrap();
+    //the longest entry in OPERATORS the input at the cursor starts with, so `!=-1` is `!=` then `-`
+    fn match_operator(&self) -> Option<&'static str> {
+        let rest = &self.input[self.position..];
 
-            if OPERATORS.contains(&c.to_string().as_str()) {
-                value.push(c);
-                self.position += 1;
-                self.column += 1;
-            } else {
-                break;
-            }
+        OPERATORS
+            .iter()
+            .filter(|operator| rest.starts_with(*operator))
+            .max_by_key(|operator| operator.len())
+            .copied()
+    }
+
+    fn read_operator(&mut self, operator: &str) -> TokenKind {
+        for _ in operator.chars() {
+            self.bump();
         }
 
-        Token::new(
-            TokenKind::Operator(Operator::new(&value)),
-            self.line,
-            self.column - value.len(),
-            self.column,
-        )
+        TokenKind::Operator(Operator::new(operator))
     }
 
-    fn read_punctuator(&mut self) -> Token {
-        let mut value = String::new();
+    //like operators the longest punctuator wins, so `::` is one token and `);` is two
+    fn match_punctuator(&self) -> Option<&'static str> {
+        let rest = &self.input[self.position..];
+
+        PUNCTUATORS
+            .iter()
+            .filter(|punctuator| rest.starts_with(*punctuator))
+            .max_by_key(|punctuator| punctuator.len())
+            .copied()
+    }
 
-        //check if char is a left bracket
-        if self.input.chars().nth(self.position).unwrap() == '[' {
-            return self.read_array();
+    fn read_punctuator(&mut self, punctuator: &str) -> TokenKind {
+        for _ in punctuator.chars() {
+            self.bump();
         }
 
-        while self.position < self.input.len() {
-            let c = self.input.chars().nth(self.position).unwrap();
+        let value = punctuator.to_string();
 
-            if PUNCTUATORS.contains(&c.to_string().as_str()) {
-                value.push(c);
-                self.position += 1;
-                self.column += 1;
-            } else {
-                break;
+        //keep track of blocks opened inside an interpolation
+        if let Some(depth) = self.interpolations.last_mut() {
+            match value.as_str() {
+                "{" => *depth += 1,
+                "}" => *depth -= 1,
+                _ => {}
             }
         }
 
-        Token::new(
-            TokenKind::Punctuator(Punctuator::new(&value)),
-            self.line,
-            self.column - value.len(),
-            self.column,
-        )
+        TokenKind::Punctuator(Punctuator::new(&value))
     }
+}
 
-    fn read_array(&mut self) -> Token {
-        let mut tokens: Vec<Option<Token>> = Vec::new();
-
-        let start = self.column;
+//the length in bytes of the block comment rest starts with, None when the input ends
+//inside it; block comments nest, so in /* a /* b */ c */ the comment ends at the last */
+fn block_comment_length(rest: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut chars = rest.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        match (c, chars.peek().map(|(_, next)| *next)) {
+            ('/', Some('*')) => {
+                chars.next();
+                depth += 1;
+            }
+            ('*', Some('/')) => {
+                chars.next();
+                depth -= 1;
 
-        self.position += 1;
-        self.column += 1;
-    
-        while self.position < self.input.len() {
-            let c = self.input.chars().nth(self.position).unwrap();
-    
-            if c == ']' {
-                self.position += 1;
-                self.column += 1;
-                break;
-            } else {
-                let token = self.next();
-                tokens.push(token);
+                if depth == 0 {
+                    return Some(i + 2);
+                }
             }
+            _ => {}
         }
-    
-        Token::new(
-            TokenKind::ArrayLiteral(ArrayLiteral::new(tokens.into_iter().flatten().collect::<Vec<Token>>())),
-            self.line,
-            start,
-            self.column,
-        )
     }
+
+    None
+}
+
+//underscores may only separate digits, so they can not start or end a group
+fn valid_digits(digits: &str, radix: u32) -> bool {
+    !digits.is_empty()
+        && !digits.starts_with('_')
+        && !digits.ends_with('_')
+        && digits.chars().all(|c| c == '_' || c.is_digit(radix))
+}
+
+//every digit group of the mantissa and the exponent has to be valid on its own
+fn valid_decimal(text: &str) -> bool {
+    let (mantissa, exponent) = match text.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, Some(exponent)),
+        None => (text, None),
+    };
+
+    let exponent = exponent.map(|exponent| exponent.trim_start_matches(['+', '-']));
+
+    mantissa.split('.').all(|digits| valid_digits(digits, 10))
+        && exponent.is_none_or(|digits| valid_digits(digits, 10))
+}
+
+fn number_error(error_type: ErrorType, message: &str, text: &str) -> TokenKind {
+    TokenKind::Error(Error::new(error_type, message, text))
 }
 
 //create error struct with a type and a message
@@ -778,6 +1877,63 @@ pub enum ErrorType {
     InvalidToken,
     UnexpectedEndOfInput,
     MissingType,
+    UndefinedVariable,
+    TypeMismatch,
+    InvalidAssignment,
+    WrongArgumentCount,
+    InvalidControlFlow,
+    MissingReturn,
+    DivisionByZero,
+    UnterminatedString,
+    UnterminatedComment,
+    InvalidEscape,
+    IntegerOverflow,
+    MalformedNumber,
+    IndexOutOfBounds,
+    UndefinedType,
+    UndefinedField,
+    MissingField,
+    UndefinedVariant,
+    NonExhaustiveMatch,
+    StackOverflow,
+    Io,
+    FloatOverflow,
+    UnsupportedType,
+}
+
+impl ErrorType {
+    //a stable code per kind of error, shown in diagnostics like error[E0006]
+    pub fn code(&self) -> &'static str {
+        match self {
+            ErrorType::UnexpectedToken => "E0001",
+            ErrorType::InvalidToken => "E0002",
+            ErrorType::UnexpectedEndOfInput => "E0003",
+            ErrorType::MissingType => "E0004",
+            ErrorType::UndefinedVariable => "E0005",
+            ErrorType::TypeMismatch => "E0006",
+            ErrorType::InvalidAssignment => "E0007",
+            ErrorType::WrongArgumentCount => "E0008",
+            ErrorType::InvalidControlFlow => "E0009",
+            ErrorType::MissingReturn => "E0010",
+            ErrorType::DivisionByZero => "E0011",
+            ErrorType::UnterminatedString => "E0012",
+            ErrorType::UnterminatedComment => "E0013",
+            ErrorType::InvalidEscape => "E0014",
+            ErrorType::IntegerOverflow => "E0015",
+            ErrorType::MalformedNumber => "E0016",
+            ErrorType::IndexOutOfBounds => "E0017",
+            ErrorType::UndefinedType => "E0018",
+            ErrorType::UndefinedField => "E0019",
+            ErrorType::MissingField => "E0020",
+            ErrorType::UndefinedVariant => "E0021",
+            ErrorType::NonExhaustiveMatch => "E0022",
+            ErrorType::StackOverflow => "E0023",
+            ErrorType::Io => "E0024",
+            //added after the others so the codes before it stay the same
+            ErrorType::FloatOverflow => "E0025",
+            ErrorType::UnsupportedType => "E0026",
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -785,6 +1941,9 @@ pub struct Error {
     error_type: ErrorType,
     message: String,
     errorstring: String,
+    //the part of the token or node the error is about, when that is narrower than all of it
+    //like a bad escape inside a string; boxed so results carrying an error stay small
+    position: Option<Box<Position>>,
 }
 
 impl Error {
@@ -793,6 +1952,35 @@ impl Error {
             error_type,
             message: message.to_string(),
             errorstring: error.to_string(),
+            position: None,
         }
     }
+
+    pub fn with_position(mut self, position: Position) -> Error {
+        self.position = Some(Box::new(position));
+        self
+    }
+
+    pub fn error_type(&self) -> &ErrorType {
+        &self.error_type
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    //the text the error is about, like the offending token or the name of a variable
+    pub fn errorstring(&self) -> &str {
+        &self.errorstring
+    }
+
+    pub fn position(&self) -> Option<&Position> {
+        self.position.as_deref()
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.message, self.errorstring)
+    }
 }
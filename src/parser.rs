@@ -0,0 +1,2610 @@
+use std::collections::HashMap;
+
+use crate::ast::{
+    BinaryOp, Block, Expr, ExprKind, Field, FieldInit, MatchArm, Param, Pattern, PatternKind,
+    PostfixOp, Program, Stmt, UnaryOp, Variant,
+};
+use crate::{
+    Error, ErrorType, FileId, Identifier, Keyword, Operator, Position, Punctuator, Span, Token,
+    TokenKind, Tokenizer, Type, KEYWORDS,
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn let_declaration() {
+        let program = parse("let x: float = 10.5;").unwrap();
+
+        let Stmt::Let {
+            name,
+            ty,
+            init,
+            position,
+            ..
+        } = &program.statements[0]
+        else {
+            panic!("expected a let statement");
+        };
+
+        assert_eq!(name, "x");
+        assert_eq!(ty, &Some(Type::new("float")));
+        assert_eq!(
+            init,
+            &Expr::new(
+                ExprKind::Float(10.5),
+                Position::new(1, 16, 20).with_bytes(15, 19)
+            )
+        );
+        assert_eq!(position, &Position::new(1, 1, 21).with_bytes(0, 20));
+    }
+
+    #[test]
+    fn const_declaration() {
+        let program = parse("const limit = 10;\nlet y = limit;").unwrap();
+
+        assert_eq!(
+            program.statements[0],
+            Stmt::Const {
+                name: "limit".to_string(),
+                name_position: Position::new(1, 7, 12).with_bytes(6, 11),
+                ty: None,
+                init: Expr::new(
+                    ExprKind::Int(10),
+                    Position::new(1, 15, 17).with_bytes(14, 16)
+                ),
+                doc: None,
+                position: Position::new(1, 1, 18).with_bytes(0, 17),
+            }
+        );
+        assert_eq!(
+            program.statements[1],
+            Stmt::Let {
+                name: "y".to_string(),
+                name_position: Position::new(2, 5, 6).with_bytes(22, 23),
+                ty: None,
+                init: Expr::new(
+                    ExprKind::Identifier("limit".to_string()),
+                    Position::new(2, 9, 14).with_bytes(26, 31)
+                ),
+                doc: None,
+                position: Position::new(2, 1, 15).with_bytes(18, 32),
+            }
+        );
+    }
+
+    #[test]
+    fn array_initializer() {
+        let program = parse("let a: array = [1, true, 3.4];").unwrap();
+
+        let Stmt::Let { init, .. } = &program.statements[0] else {
+            panic!("expected a let statement");
+        };
+
+        assert_eq!(
+            init.kind,
+            ExprKind::Array(vec![
+                Expr::new(
+                    ExprKind::Int(1),
+                    Position::new(1, 17, 18).with_bytes(16, 17)
+                ),
+                Expr::new(
+                    ExprKind::Bool(true),
+                    Position::new(1, 20, 24).with_bytes(19, 23)
+                ),
+                Expr::new(
+                    ExprKind::Float(3.4),
+                    Position::new(1, 26, 29).with_bytes(25, 28)
+                ),
+            ])
+        );
+        assert_eq!(init.position, Position::new(1, 16, 30).with_bytes(15, 29));
+    }
+
+    //render an expression as an s-expression so tree shapes are easy to compare
+    fn sexpr(expr: &Expr) -> String {
+        match &expr.kind {
+            ExprKind::Int(value) => value.to_string(),
+            ExprKind::Float(value) => value.to_string(),
+            ExprKind::String(value) => format!("{:?}", value),
+            ExprKind::Bool(value) => value.to_string(),
+            ExprKind::Identifier(name) => name.clone(),
+            ExprKind::Array(elements) => {
+                let elements = elements.iter().map(sexpr).collect::<Vec<String>>();
+                format!("[{}]", elements.join(" "))
+            }
+            ExprKind::Interpolation(parts) => {
+                let parts = parts.iter().map(sexpr).collect::<Vec<String>>();
+                format!("(str {})", parts.join(" "))
+            }
+            ExprKind::Unary { op, operand } => format!("({} {})", op.as_str(), sexpr(operand)),
+            ExprKind::Postfix { op, operand } => format!("({} {})", sexpr(operand), op.as_str()),
+            ExprKind::Binary { op, left, right } => {
+                format!("({} {} {})", op.as_str(), sexpr(left), sexpr(right))
+            }
+            ExprKind::Call { callee, args } => {
+                let args = args.iter().map(sexpr).collect::<Vec<String>>();
+                format!("(call {} {})", sexpr(callee), args.join(" "))
+            }
+            ExprKind::Assign { target, value } => format!("(= {} {})", sexpr(target), sexpr(value)),
+            ExprKind::Index { target, index } => {
+                format!("(index {} {})", sexpr(target), sexpr(index))
+            }
+            ExprKind::StructLiteral { name, fields } => {
+                let fields = fields
+                    .iter()
+                    .map(|field| format!(" {}: {}", field.name, sexpr(&field.value)))
+                    .collect::<String>();
+                format!("({}{})", name, fields)
+            }
+            ExprKind::Field { target, name } => format!("(. {} {})", sexpr(target), name),
+            ExprKind::Error => "<error>".to_string(),
+            ExprKind::Variant {
+                enum_name,
+                variant,
+                args,
+            } if args.is_empty() => format!("{}::{}", enum_name, variant),
+            ExprKind::Variant {
+                enum_name,
+                variant,
+                args,
+            } => {
+                let args = args.iter().map(sexpr).collect::<Vec<String>>();
+                format!("({}::{} {})", enum_name, variant, args.join(" "))
+            }
+            ExprKind::Match { subject, arms } => {
+                let arms = arms
+                    .iter()
+                    .map(|arm| match &arm.guard {
+                        Some(guard) => format!(
+                            " ({} if {} => {})",
+                            sexpr_pattern(&arm.pattern),
+                            sexpr(guard),
+                            sexpr(&arm.body)
+                        ),
+                        None => {
+                            format!(" ({} => {})", sexpr_pattern(&arm.pattern), sexpr(&arm.body))
+                        }
+                    })
+                    .collect::<String>();
+                format!("(match {}{})", sexpr(subject), arms)
+            }
+        }
+    }
+
+    fn sexpr_pattern(pattern: &Pattern) -> String {
+        match &pattern.kind {
+            PatternKind::Wildcard => "_".to_string(),
+            PatternKind::Literal(value) => sexpr(value),
+            PatternKind::Binding(name) => name.clone(),
+            PatternKind::Variant {
+                enum_name,
+                variant,
+                fields,
+            } if fields.is_empty() => format!("{}::{}", enum_name, variant),
+            PatternKind::Variant {
+                enum_name,
+                variant,
+                fields,
+            } => {
+                let fields = fields.iter().map(sexpr_pattern).collect::<Vec<String>>();
+                format!("({}::{} {})", enum_name, variant, fields.join(" "))
+            }
+        }
+    }
+
+    fn parse_expr(input: &str) -> Expr {
+        let mut parser = Parser::new(input);
+        let expr = parser.parse_expression().unwrap();
+
+        assert!(parser.at_end(), "trailing tokens after {}", input);
+        expr
+    }
+
+    #[test]
+    fn precedence() {
+        let expr = parse_expr("1 + 2 * 3 == 7 && !false");
+
+        assert_eq!(sexpr(&expr), "(&& (== (+ 1 (* 2 3)) 7) (! false))");
+        assert_eq!(expr.position, Position::new(1, 1, 25).with_bytes(0, 24));
+    }
+
+    #[test]
+    fn left_associative() {
+        assert_eq!(sexpr(&parse_expr("10 - 4 - 3")), "(- (- 10 4) 3)");
+        assert_eq!(sexpr(&parse_expr("8 / 4 % 3")), "(% (/ 8 4) 3)");
+        assert_eq!(
+            sexpr(&parse_expr("a || b || c && d")),
+            "(|| (|| a b) (&& c d))"
+        );
+    }
+
+    #[test]
+    fn comparison_binds_tighter_than_equality() {
+        assert_eq!(sexpr(&parse_expr("a < b == c > d")), "(== (< a b) (> c d))");
+    }
+
+    #[test]
+    fn unary_and_postfix() {
+        assert_eq!(sexpr(&parse_expr("-x * 2")), "(* (- x) 2)");
+        assert_eq!(sexpr(&parse_expr("!!done")), "(! (! done))");
+        assert_eq!(sexpr(&parse_expr("a!=-1")), "(!= a (- 1))");
+        assert_eq!(sexpr(&parse_expr("a<=b")), "(<= a b)");
+        assert_eq!(sexpr(&parse_expr("-i++ + ++j")), "(+ (- (i ++)) (++ j))");
+        assert_eq!(sexpr(&parse_expr("i--")), "(i --)");
+    }
+
+    #[test]
+    fn grouping() {
+        let expr = parse_expr("(1 + 2) * 3");
+
+        assert_eq!(sexpr(&expr), "(* (+ 1 2) 3)");
+        assert_eq!(expr.position, Position::new(1, 1, 12).with_bytes(0, 11));
+
+        assert_eq!(sexpr(&parse_expr("((a))")), "a");
+    }
+
+    #[test]
+    fn unclosed_paren() {
+        let error = parse("let x = (1 + 2;").unwrap_err();
+
+        assert_eq!(
+            error,
+            ParseError::new(
+                Error::new(ErrorType::UnexpectedToken, "Expected `)`, found `;`", "`;`"),
+                Position::new(1, 15, 16).with_bytes(14, 15),
+            )
+        );
+    }
+
+    #[test]
+    fn function_declaration() {
+        let program = parse("func add(a: int, b: float): float {\n  return a + b;\n}").unwrap();
+
+        let Stmt::Func {
+            name,
+            name_position,
+            params,
+            return_ty,
+            body,
+            position,
+            ..
+        } = &program.statements[0]
+        else {
+            panic!("expected a function");
+        };
+
+        assert_eq!(name, "add");
+        assert_eq!(name_position, &Position::new(1, 6, 9).with_bytes(5, 8));
+        assert_eq!(
+            params
+                .iter()
+                .map(|param| (param.name.as_str(), param.ty.clone()))
+                .collect::<Vec<_>>(),
+            [("a", Type::Int), ("b", Type::Float)]
+        );
+        assert_eq!(return_ty, &Some(Type::new("float")));
+        assert_eq!(position.line, 1);
+
+        let Stmt::Return {
+            value: Some(value), ..
+        } = &body.statements[0]
+        else {
+            panic!("expected a return statement");
+        };
+
+        assert_eq!(sexpr(value), "(+ a b)");
+    }
+
+    #[test]
+    fn function_without_return_type() {
+        let program = parse("func log() { return; }").unwrap();
+
+        let Stmt::Func {
+            params,
+            return_ty,
+            body,
+            ..
+        } = &program.statements[0]
+        else {
+            panic!("expected a function");
+        };
+
+        assert!(params.is_empty());
+        assert_eq!(return_ty, &None);
+        assert_eq!(
+            body.statements,
+            vec![Stmt::Return {
+                value: None,
+                position: Position::new(1, 14, 21).with_bytes(13, 20),
+            }]
+        );
+        assert_eq!(body.position, Position::new(1, 12, 23).with_bytes(11, 22));
+    }
+
+    #[test]
+    fn annotations() {
+        let program =
+            parse("let x : int = 1;\nfunc f(a :float , b:bool) : string { return \"\"; }").unwrap();
+
+        assert!(matches!(
+            &program.statements[0],
+            Stmt::Let {
+                ty: Some(Type::Int),
+                ..
+            }
+        ));
+
+        let Stmt::Func {
+            params, return_ty, ..
+        } = &program.statements[1]
+        else {
+            panic!("expected a function");
+        };
+
+        assert_eq!(params[0].ty, Type::new("float"));
+        assert_eq!(
+            params[1].position,
+            Position::new(2, 19, 20).with_bytes(35, 36)
+        );
+        assert_eq!(return_ty, &Some(Type::new("string")));
+    }
+
+    #[test]
+    fn composite_types() {
+        let annotation = |ty: &str| {
+            let program = parse(&format!("let x: {} = 0;", ty)).unwrap();
+
+            match &program.statements[0] {
+                Stmt::Let { ty: Some(ty), .. } => ty.clone(),
+                stmt => panic!("expected an annotated let, found {:?}", stmt),
+            }
+        };
+
+        assert_eq!(
+            annotation("array<int>"),
+            Type::Array(Some(Box::new(Type::Int)))
+        );
+        assert_eq!(
+            annotation("map<string, float>"),
+            Type::Map(Box::new(Type::String), Box::new(Type::Float))
+        );
+        assert_eq!(annotation("int?"), Type::Optional(Box::new(Type::Int)));
+        assert_eq!(
+            annotation("func(int, int): bool"),
+            Type::Func(vec![Type::Int, Type::Int], Some(Box::new(Type::Bool)))
+        );
+        assert_eq!(
+            annotation("(int, string)"),
+            Type::Tuple(vec![Type::Int, Type::String])
+        );
+        assert_eq!(annotation("Point"), Type::Named("Point".to_string()));
+
+        //the written form comes back out of Display
+        for ty in [
+            "array",
+            "array<array<int?>>",
+            "map<string, array<Point>>",
+            "func()",
+            "func(func(int): int, string?): (int, bool)",
+            "(func(): int)?",
+            "(int,)",
+            "()",
+        ] {
+            assert_eq!(annotation(ty).to_string(), ty);
+        }
+
+        assert_eq!(annotation("(int)"), Type::Int);
+        assert_eq!(annotation("int??").to_string(), "int??");
+    }
+
+    #[test]
+    fn type_arguments_before_assignment() {
+        //without a space the closing `>` and the `=` are lexed as one `>=`
+        let program = parse("let a: array<int>= [1];\nlet m: map<string, array<int>>= a;").unwrap();
+
+        let Stmt::Let { ty, init, .. } = &program.statements[0] else {
+            panic!("expected a let");
+        };
+
+        assert_eq!(ty, &Some(Type::Array(Some(Box::new(Type::Int)))));
+        assert_eq!(init.position, Position::new(1, 20, 23).with_bytes(19, 22));
+
+        let Stmt::Let { ty, init, .. } = &program.statements[1] else {
+            panic!("expected a let");
+        };
+
+        assert_eq!(
+            ty.as_ref().map(Type::to_string).as_deref(),
+            Some("map<string, array<int>>")
+        );
+        assert!(matches!(&init.kind, ExprKind::Identifier(name) if name == "a"));
+    }
+
+    #[test]
+    fn malformed_types() {
+        let error = |source: &str| parse(source).unwrap_err().error;
+
+        assert_eq!(
+            error("let x: array<int = 1;"),
+            Error::new(ErrorType::UnexpectedToken, "Expected `>`, found `=`", "`=`")
+        );
+        assert_eq!(
+            error("let x: map<int> = 1;"),
+            Error::new(
+                ErrorType::UnexpectedToken,
+                "Expected `,`, found operator `>`",
+                "operator `>`"
+            )
+        );
+        assert_eq!(
+            error("let x: 5 = 1;"),
+            Error::new(
+                ErrorType::UnexpectedToken,
+                "Expected a type, found `5`",
+                "`5`"
+            )
+        );
+    }
+
+    #[test]
+    fn untyped_parameter() {
+        let error = parse("func f(a) {}").unwrap_err();
+
+        assert_eq!(
+            error,
+            ParseError::new(
+                Error::new(
+                    ErrorType::UnexpectedToken,
+                    "Expected a parameter type, found `)`",
+                    "`)`"
+                ),
+                Position::new(1, 9, 10).with_bytes(8, 9),
+            )
+        );
+    }
+
+    #[test]
+    fn calls() {
+        assert_eq!(sexpr(&parse_expr("name(1, 2.5)")), "(call name 1 2.5)");
+        assert_eq!(sexpr(&parse_expr("add(1, 2) * 3")), "(* (call add 1 2) 3)");
+        assert_eq!(
+            sexpr(&parse_expr("f(g(x), -y)")),
+            "(call f (call g x) (- y))"
+        );
+        assert_eq!(sexpr(&parse_expr("now()")), "(call now )");
+
+        let program = parse("print(x);").unwrap();
+
+        assert!(matches!(program.statements[0], Stmt::Expr { .. }));
+    }
+
+    #[test]
+    fn if_else_chain() {
+        let program = parse("if x < 1 { a(); } else if x < 2 { b(); } else { c(); }").unwrap();
+
+        let Stmt::If {
+            condition,
+            then_branch,
+            else_branch: Some(else_branch),
+            position,
+        } = &program.statements[0]
+        else {
+            panic!("expected an if statement");
+        };
+
+        assert_eq!(sexpr(condition), "(< x 1)");
+        assert_eq!(then_branch.statements.len(), 1);
+        assert_eq!(position, &Position::new(1, 1, 55).with_bytes(0, 54));
+
+        let Stmt::If {
+            condition,
+            else_branch: Some(else_branch),
+            ..
+        } = else_branch.as_ref()
+        else {
+            panic!("expected an else if");
+        };
+
+        assert_eq!(sexpr(condition), "(< x 2)");
+        assert!(matches!(else_branch.as_ref(), Stmt::Block(block) if block.statements.len() == 1));
+    }
+
+    #[test]
+    fn while_loop() {
+        let program = parse("while i < 10 { i = i + 1; if done { break; } continue; }").unwrap();
+
+        let Stmt::While {
+            condition, body, ..
+        } = &program.statements[0]
+        else {
+            panic!("expected a while loop");
+        };
+
+        assert_eq!(sexpr(condition), "(< i 10)");
+
+        let Stmt::Expr { expr, .. } = &body.statements[0] else {
+            panic!("expected an assignment");
+        };
+
+        assert_eq!(sexpr(expr), "(= i (+ i 1))");
+        assert!(matches!(body.statements[2], Stmt::Continue { .. }));
+    }
+
+    #[test]
+    fn c_style_for() {
+        let program = parse("for (let i: int = 0; i < n; i++) { total = total + i; }").unwrap();
+
+        let Stmt::For {
+            init: Some(init),
+            condition: Some(condition),
+            update: Some(update),
+            body,
+            ..
+        } = &program.statements[0]
+        else {
+            panic!("expected a for loop");
+        };
+
+        assert!(matches!(init.as_ref(), Stmt::Let { name, .. } if name == "i"));
+        assert_eq!(sexpr(condition), "(< i n)");
+        assert_eq!(sexpr(update), "(i ++)");
+        assert_eq!(body.statements.len(), 1);
+
+        let program = parse("for (;;) { break; }").unwrap();
+
+        assert!(matches!(
+            &program.statements[0],
+            Stmt::For {
+                init: None,
+                condition: None,
+                update: None,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn for_in() {
+        let program = parse("for x in [1, 2, 3] { print(x); }").unwrap();
+
+        let Stmt::ForIn {
+            name,
+            name_position,
+            iterable,
+            body,
+            position,
+        } = &program.statements[0]
+        else {
+            panic!("expected a for in loop");
+        };
+
+        assert_eq!(name, "x");
+        assert_eq!(name_position, &Position::new(1, 5, 6).with_bytes(4, 5));
+        assert_eq!(sexpr(iterable), "[1 2 3]");
+        assert_eq!(body.statements.len(), 1);
+        assert_eq!(position, &Position::new(1, 1, 33).with_bytes(0, 32));
+    }
+
+    #[test]
+    fn nested_blocks() {
+        let program = parse("{ let x = 1; { let y = x; } }").unwrap();
+
+        let Stmt::Block(block) = &program.statements[0] else {
+            panic!("expected a block");
+        };
+
+        assert!(matches!(block.statements[1], Stmt::Block(_)));
+    }
+
+    #[test]
+    fn assignment() {
+        assert_eq!(sexpr(&parse_expr("a = b = 1 + 2")), "(= a (= b (+ 1 2)))");
+
+        let error = parse("1 + 2 = 3;").unwrap_err();
+
+        assert_eq!(
+            error,
+            ParseError::new(
+                Error::new(
+                    ErrorType::UnexpectedToken,
+                    "Invalid assignment target",
+                    "expression"
+                ),
+                Position::new(1, 1, 6).with_bytes(0, 5),
+            )
+        );
+    }
+
+    #[test]
+    fn array_literals() {
+        assert_eq!(sexpr(&parse_expr("[]")), "[]");
+        assert_eq!(sexpr(&parse_expr("[x + 1, f(y)]")), "[(+ x 1) (call f y)]");
+        assert_eq!(sexpr(&parse_expr("[[1, 2], [3]]")), "[[1 2] [3]]");
+
+        let expr = parse_expr("[\n  1,\n  2,\n]");
+
+        assert_eq!(sexpr(&expr), "[1 2]");
+        assert_eq!(expr.position.line, 1);
+    }
+
+    #[test]
+    fn interpolation() {
+        assert_eq!(
+            sexpr(&parse_expr("\"hello ${name}, you are ${age + 1}\"")),
+            "(str \"hello \" name \", you are \" (+ age 1))"
+        );
+        assert_eq!(sexpr(&parse_expr("\"${a}${b}\"")), "(str a b)");
+        assert_eq!(
+            sexpr(&parse_expr("\"${\"${x}!\"} ${f(y)}\"")),
+            "(str (str x \"!\") \" \" (call f y))"
+        );
+
+        let expr = parse_expr("\"a ${x} b\" + c");
+
+        assert_eq!(expr.position, Position::new(1, 1, 15).with_bytes(0, 14));
+        assert_eq!(
+            parse("\"a ${x y}\";").unwrap_err().error,
+            Error::new(
+                ErrorType::UnexpectedToken,
+                "Expected `}`, found identifier `y`",
+                "identifier `y`"
+            )
+        );
+    }
+
+    #[test]
+    fn index_expressions() {
+        assert_eq!(sexpr(&parse_expr("a[0]")), "(index a 0)");
+        assert_eq!(
+            sexpr(&parse_expr("grid[i][j + 1]")),
+            "(index (index grid i) (+ j 1))"
+        );
+        assert_eq!(
+            sexpr(&parse_expr("rows()[0] * 2")),
+            "(* (index (call rows ) 0) 2)"
+        );
+        assert_eq!(sexpr(&parse_expr("[1, 2][1]")), "(index [1 2] 1)");
+        assert_eq!(
+            sexpr(&parse_expr("a[0] = a[1]")),
+            "(= (index a 0) (index a 1))"
+        );
+
+        let expr = parse_expr("a[0]");
+
+        assert_eq!(expr.position, Position::new(1, 1, 5).with_bytes(0, 4));
+    }
+
+    #[test]
+    fn structs() {
+        let program = parse("struct Point { x: float, y: float, }").unwrap();
+
+        let Stmt::Struct {
+            name,
+            name_position,
+            fields,
+            position,
+            ..
+        } = &program.statements[0]
+        else {
+            panic!("expected a struct");
+        };
+
+        assert_eq!(name, "Point");
+        assert_eq!(name_position, &Position::new(1, 8, 13).with_bytes(7, 12));
+        assert_eq!(fields.len(), 2);
+        assert_eq!(fields[1].name, "y");
+        assert_eq!(fields[1].ty, Type::Float);
+        assert_eq!(
+            fields[1].position,
+            Position::new(1, 26, 27).with_bytes(25, 26)
+        );
+        assert_eq!(position, &Position::new(1, 1, 37).with_bytes(0, 36));
+
+        assert_eq!(
+            sexpr(&parse_expr("Point { x: 1.5, y: a + 1 }")),
+            "(Point x: 1.5 y: (+ a 1))"
+        );
+        assert_eq!(sexpr(&parse_expr("Empty {}")), "(Empty)");
+        assert_eq!(
+            sexpr(&parse_expr("p.x + p.pos.y")),
+            "(+ (. p x) (. (. p pos) y))"
+        );
+        assert_eq!(sexpr(&parse_expr("p.x = 2")), "(= (. p x) 2)");
+        assert_eq!(sexpr(&parse_expr("p.len()")), "(call (. p len) )");
+    }
+
+    #[test]
+    fn declarations_only_at_the_top_level() {
+        let source = "struct P { x: int }\nfunc g(): int { struct P { y: string } return 1; }\n{ enum E { A } impl P { } }";
+        let (program, errors) = Parser::new(source).parse_recovering();
+
+        assert_eq!(
+            errors,
+            [
+                ParseError::new(
+                    Error::new(
+                        ErrorType::UnexpectedToken,
+                        "`struct` is only allowed at the top level",
+                        "struct"
+                    ),
+                    Position::new(2, 17, 23).with_bytes(36, 42),
+                ),
+                ParseError::new(
+                    Error::new(
+                        ErrorType::UnexpectedToken,
+                        "`enum` is only allowed at the top level",
+                        "enum"
+                    ),
+                    Position::new(3, 3, 7).with_bytes(73, 77),
+                ),
+                ParseError::new(
+                    Error::new(
+                        ErrorType::UnexpectedToken,
+                        "`impl` is only allowed at the top level",
+                        "impl"
+                    ),
+                    Position::new(3, 16, 20).with_bytes(86, 90),
+                ),
+            ]
+        );
+        //the declarations still parse, so the rest of the program is unaffected
+        assert_eq!(program.statements.len(), 3);
+    }
+
+    #[test]
+    fn struct_literals_are_not_parsed_in_conditions() {
+        let program = parse("if x { y; }").unwrap();
+
+        let Stmt::If { condition, .. } = &program.statements[0] else {
+            panic!("expected an if");
+        };
+
+        assert_eq!(sexpr(condition), "x");
+
+        let program = parse("while (p == P { x: 1 }) { }").unwrap();
+
+        let Stmt::While { condition, .. } = &program.statements[0] else {
+            panic!("expected a while");
+        };
+
+        assert_eq!(sexpr(condition), "(== p (P x: 1))");
+
+        //an interpolation is bracketed like a paren, so struct literals work inside it
+        let program = parse("if \"${P { x: 1 }.x}\" == \"1\" { }").unwrap();
+
+        let Stmt::If { condition, .. } = &program.statements[0] else {
+            panic!("expected an if");
+        };
+
+        assert_eq!(sexpr(condition), "(== (str (. (P x: 1) x)) \"1\")");
+    }
+
+    #[test]
+    fn impl_blocks() {
+        let program =
+            parse("impl Point { func len(self): float { return self.x; } func zero(self) { } }")
+                .unwrap();
+
+        let Stmt::Impl { name, methods, .. } = &program.statements[0] else {
+            panic!("expected an impl");
+        };
+
+        assert_eq!(name, "Point");
+        assert_eq!(methods.len(), 2);
+
+        let Stmt::Func { name, params, .. } = &methods[0] else {
+            panic!("expected a method");
+        };
+
+        assert_eq!(name, "len");
+        assert_eq!(params[0].name, "self");
+        assert_eq!(params[0].ty, Type::Named("Point".to_string()));
+        assert_eq!(
+            parse("impl Point { func f(x: int) { } }")
+                .unwrap_err()
+                .error,
+            Error::new(
+                ErrorType::UnexpectedToken,
+                "Expected `self`, found identifier `x`",
+                "identifier `x`"
+            )
+        );
+        assert_eq!(
+            parse("impl Point { let x = 1; }").unwrap_err().error,
+            Error::new(
+                ErrorType::UnexpectedToken,
+                "Expected a method, found keyword `let`",
+                "keyword `let`"
+            )
+        );
+    }
+
+    #[test]
+    fn enums() {
+        let program = parse("enum Shape { Circle(float), Rect(float, float), Empty }").unwrap();
+
+        let Stmt::Enum { name, variants, .. } = &program.statements[0] else {
+            panic!("expected an enum");
+        };
+
+        assert_eq!(name, "Shape");
+        assert_eq!(variants.len(), 3);
+        assert_eq!(variants[1].name, "Rect");
+        assert_eq!(variants[1].fields, vec![Type::Float, Type::Float]);
+        assert_eq!(
+            variants[1].position,
+            Position::new(1, 29, 33).with_bytes(28, 32)
+        );
+        assert!(variants[2].fields.is_empty());
+
+        assert_eq!(
+            sexpr(&parse_expr("Shape::Circle(r * 2)")),
+            "(Shape::Circle (* r 2))"
+        );
+        assert_eq!(
+            sexpr(&parse_expr("Shape::Empty == s")),
+            "(== Shape::Empty s)"
+        );
+        assert_eq!(
+            parse_expr("Shape::Rect(1, 2)").position,
+            Position::new(1, 1, 18).with_bytes(0, 17)
+        );
+    }
+
+    #[test]
+    fn match_expressions() {
+        assert_eq!(
+            sexpr(&parse_expr(
+                "match s { Shape::Circle(r) => r * r, Shape::Rect(w, _) if w > 0 => w, _ => 0 }"
+            )),
+            "(match s ((Shape::Circle r) => (* r r)) ((Shape::Rect w _) if (> w 0) => w) (_ => 0))"
+        );
+        assert_eq!(
+            sexpr(&parse_expr("match n { 0 => \"zero\", -1 => \"minus one\", 1.5 => \"\", true => P { x: 1 }, n => n, }")),
+            "(match n (0 => \"zero\") (-1 => \"minus one\") (1.5 => \"\") (true => (P x: 1)) (n => n))"
+        );
+        assert_eq!(
+            sexpr(&parse_expr(
+                "match o { Opt::Some(Shape::Empty) => 1, Opt::None => 2 }"
+            )),
+            "(match o ((Opt::Some Shape::Empty) => 1) (Opt::None => 2))"
+        );
+
+        let program = parse("match x { _ => print(x) }\nlet y = match x { _ => 1 };").unwrap();
+
+        assert_eq!(program.statements.len(), 2);
+        assert_eq!(
+            program.statements[0].position(),
+            &Position::new(1, 1, 26).with_bytes(0, 25)
+        );
+        assert_eq!(
+            parse("match x { 1 + 2 => 3 }").unwrap_err().error,
+            Error::new(
+                ErrorType::UnexpectedToken,
+                "Expected `=>`, found operator `+`",
+                "operator `+`"
+            )
+        );
+        assert_eq!(
+            parse("match x { [1] => 3 }").unwrap_err().error,
+            Error::new(
+                ErrorType::UnexpectedToken,
+                "Expected a pattern, found `[`",
+                "`[`"
+            )
+        );
+    }
+
+    #[test]
+    fn unclosed_array() {
+        let error = parse("let a = [1, 2;").unwrap_err();
+
+        assert_eq!(
+            error,
+            ParseError::new(
+                Error::new(ErrorType::UnexpectedToken, "Expected `,`, found `;`", "`;`"),
+                Position::new(1, 14, 15).with_bytes(13, 14),
+            )
+        );
+    }
+
+    #[test]
+    fn doc_comments() {
+        let source = "
+/// Adds two numbers.
+///
+/// Works for ints only.
+func add(a: int, b: int): int {
+    /// the sum
+    let sum = a + b;
+    /// dropped, nothing is declared here
+    return sum;
+}
+
+/* not a doc comment */
+const limit = 10;
+/// trailing";
+        let program = parse(source).unwrap();
+
+        let Stmt::Func { doc, body, .. } = &program.statements[0] else {
+            panic!("expected a function");
+        };
+
+        assert_eq!(
+            doc.as_deref(),
+            Some(" Adds two numbers.\n\n Works for ints only.")
+        );
+        assert!(
+            matches!(&body.statements[0], Stmt::Let { doc: Some(doc), .. } if doc == " the sum")
+        );
+        assert!(matches!(
+            &program.statements[1],
+            Stmt::Const { doc: None, .. }
+        ));
+        assert_eq!(program.statements.len(), 2);
+    }
+
+    #[test]
+    fn missing_semicolon() {
+        let error = parse("let x = 1 let").unwrap_err();
+
+        assert_eq!(
+            error,
+            ParseError::new(
+                Error::new(
+                    ErrorType::UnexpectedToken,
+                    "Expected `;`, found keyword `let`",
+                    "keyword `let`"
+                ),
+                Position::new(1, 11, 14).with_bytes(10, 13),
+            )
+        );
+    }
+
+    #[test]
+    fn unexpected_end_of_input() {
+        let error = parse("const x =").unwrap_err();
+
+        assert_eq!(
+            error,
+            ParseError::new(
+                Error::new(
+                    ErrorType::UnexpectedEndOfInput,
+                    "Expected an expression, found end of input",
+                    "end of input"
+                ),
+                Position::new(1, 10, 10).with_bytes(9, 9),
+            )
+        );
+    }
+
+    fn recover(input: &str) -> (Program, Vec<String>) {
+        let (program, errors) = Parser::new(input).parse_recovering();
+        let messages = errors
+            .into_iter()
+            .map(|error| error.error.message)
+            .collect();
+
+        (program, messages)
+    }
+
+    #[test]
+    fn recovers_at_statement_boundaries() {
+        let (program, errors) = recover(
+            "let = 1;\nlet y = 2;\nfunc f( { let q = 1; }\nlet z = 3 4;\nconst w = 5\nlet v = 6;",
+        );
+
+        assert_eq!(
+            errors,
+            [
+                "Expected an identifier, found `=`",
+                "Expected a parameter, found `{`",
+                "Expected `;`, found `4`",
+                "Expected `;`, found keyword `let`",
+            ]
+        );
+        assert_eq!(program.statements.len(), 6);
+        assert_eq!(
+            program.statements[0],
+            Stmt::Error {
+                position: Position::new(1, 1, 9).with_bytes(0, 8)
+            }
+        );
+        assert!(matches!(&program.statements[1], Stmt::Let { name, .. } if name == "y"));
+        assert!(matches!(program.statements[2], Stmt::Error { .. }));
+        assert!(matches!(program.statements[3], Stmt::Error { .. }));
+        assert!(matches!(program.statements[4], Stmt::Error { .. }));
+        assert!(matches!(&program.statements[5], Stmt::Let { name, .. } if name == "v"));
+    }
+
+    #[test]
+    fn recovers_inside_blocks() {
+        let (program, errors) = recover(
+            "func f() {\n  let a = ;\n  1 +* 2;\n  while { }\n  return 1;\n}\n}\nlet b = f(1, );",
+        );
+
+        assert_eq!(
+            errors,
+            [
+                "Expected an expression, found `;`",
+                "Expected an expression, found operator `*`",
+                "Expected an expression, found `{`",
+                "Expected an expression, found `}`",
+            ]
+        );
+        assert_eq!(program.statements.len(), 3);
+
+        let Stmt::Func { body, .. } = &program.statements[0] else {
+            panic!("expected a function");
+        };
+
+        assert_eq!(body.statements.len(), 4);
+        assert!(
+            matches!(&body.statements[0], Stmt::Let { init, .. } if init.kind == ExprKind::Error)
+        );
+        assert!(matches!(body.statements[1], Stmt::Error { .. }));
+        assert!(matches!(body.statements[3], Stmt::Return { .. }));
+        assert!(matches!(program.statements[2], Stmt::Let { .. }));
+    }
+
+    #[test]
+    fn recovers_after_lexer_errors() {
+        let (program, errors) = recover(
+            "let a = #;\nlet c = 1 = 2;\nlet d = $;\nlet b = \"strings may span lines;\nlet e = 1;",
+        );
+
+        assert_eq!(
+            errors,
+            [
+                "Invalid token",
+                "Invalid assignment target",
+                "Invalid token",
+                "Unterminated string",
+            ]
+        );
+        assert_eq!(program.statements.len(), 4);
+        assert!(matches!(program.statements[1], Stmt::Let { .. }));
+
+        //lexer errors in the part of a statement skipped by recovery are reported too
+        assert_eq!(
+            recover("let a = [1 @ 2, 3 # 4];").1,
+            ["Invalid token", "Invalid token"]
+        );
+        assert_eq!(
+            recover("let a = \"\\q\" + \"\\w\";").1,
+            ["Invalid escape sequence", "Invalid escape sequence"]
+        );
+        assert_eq!(
+            recover("f(1 @ 2, 0x_);\nlet b = 1;").1,
+            ["Invalid token", "Malformed number"]
+        );
+
+        let (_, errors) = Parser::new("let a = @ $;").parse_recovering();
+
+        assert_eq!(errors.len(), 2);
+        assert_eq!(
+            errors[1].position,
+            Position::new(1, 11, 12).with_bytes(10, 11)
+        );
+        assert_eq!(
+            parse("let a = #; let b = 1 2;").unwrap_err().error,
+            Error::new(ErrorType::InvalidToken, "Invalid token", "#")
+        );
+    }
+
+    #[test]
+    fn deep_nesting() {
+        let nested = |depth: usize, open: &str, close: &str| {
+            format!("{}{}{}", open.repeat(depth), "1", close.repeat(depth))
+        };
+
+        assert!(parse(&format!("let x = {};", nested(60, "(", ")"))).is_ok());
+
+        //deeper input is an error and not a stack overflow
+        for source in [
+            format!("let x = {};", nested(1000, "(", ")")),
+            format!("let x = {};", nested(20000, "-", "")),
+            nested(20000, "{", "}"),
+            format!("let x: {} = 1;", nested(1000, "array<", ">")),
+        ] {
+            let error = parse(&source).unwrap_err();
+
+            assert_eq!(error.error.error_type, ErrorType::StackOverflow);
+            assert_eq!(error.error.message, "Too deeply nested");
+        }
+    }
+
+    #[test]
+    fn lexer_error() {
+        let error = parse("let x = #;").unwrap_err();
+
+        assert_eq!(
+            error.error,
+            Error::new(ErrorType::InvalidToken, "Invalid token", "#")
+        );
+    }
+}
+
+//a syntax error and where it happened
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub error: Error,
+    pub position: Position,
+}
+
+impl ParseError {
+    pub fn new(error: Error, position: Position) -> Self {
+        ParseError { error, position }
+    }
+}
+
+//parse a whole source string into a program
+pub fn parse(input: &str) -> Result<Program, ParseError> {
+    Parser::new(input).parse()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    //doc comments are taken out of the token list and kept by the index of the token
+    //that follows them, so only declarations have to care about them
+    docs: HashMap<usize, String>,
+    //false while parsing an if/while condition or a for-in iterable, there `name {` starts
+    //the body and not a struct literal
+    allow_struct_literal: bool,
+    //how many blocks the parser is inside of, a `}` outside of any block is skipped
+    //by error recovery
+    block_depth: usize,
+    //every error reported so far, parsing goes on after each of them
+    errors: Vec<ParseError>,
+    //how many statements, expressions, types and patterns the parser is inside of
+    depth: usize,
+}
+
+//each level of nesting is a few recursive calls, a debug build needs about 16kb of stack
+//for a level of parens, so this keeps deep input well inside a 2mb thread and reports
+//anything deeper instead of overflowing the stack
+const MAX_DEPTH: usize = 64;
+
+impl Parser {
+    pub fn new(input: &str) -> Self {
+        Parser::from_tokens(Tokenizer::new(input).collect())
+    }
+
+    //parse input as the file with the given id, so every span points into that file
+    pub fn with_file(input: &str, file: FileId) -> Self {
+        Parser::from_tokens(Tokenizer::with_file(input, file).collect())
+    }
+
+    //the token list has to end with an eof token
+    pub fn from_tokens(tokens: Vec<Token>) -> Self {
+        let mut docs = HashMap::new();
+        let mut doc: Option<String> = None;
+        let mut rest = Vec::new();
+
+        for token in tokens {
+            match token.kind {
+                //consecutive /// lines make up a single doc comment
+                TokenKind::DocComment(line) => match &mut doc {
+                    Some(doc) => {
+                        doc.push('\n');
+                        doc.push_str(&line);
+                    }
+                    None => doc = Some(line),
+                },
+                _ => {
+                    if let Some(doc) = doc.take() {
+                        docs.insert(rest.len(), doc);
+                    }
+                    rest.push(token);
+                }
+            }
+        }
+
+        Parser {
+            tokens: rest,
+            position: 0,
+            docs,
+            allow_struct_literal: true,
+            block_depth: 0,
+            errors: Vec::new(),
+            depth: 0,
+        }
+    }
+
+    //parse the whole input and return the first syntax error, if any
+    pub fn parse(&mut self) -> Result<Program, ParseError> {
+        let (program, mut errors) = self.parse_recovering();
+
+        if errors.is_empty() {
+            Ok(program)
+        } else {
+            Err(errors.remove(0))
+        }
+    }
+
+    //parse the whole input, statements that fail to parse become Stmt::Error and parsing
+    //picks up again after them so every independent error is reported
+    pub fn parse_recovering(&mut self) -> (Program, Vec<ParseError>) {
+        let mut statements = Vec::new();
+
+        while !self.at_end() {
+            statements.push(self.parse_statement_recovering());
+        }
+
+        (Program { statements }, std::mem::take(&mut self.errors))
+    }
+
+    fn parse_statement_recovering(&mut self) -> Stmt {
+        let start = self.position;
+
+        match self.nested(Parser::parse_statement) {
+            Ok(stmt) => stmt,
+            Err(error) => {
+                self.errors.push(error);
+                self.synchronize(start);
+
+                let first = &self.tokens[start].position;
+                let last = &self.tokens[self.position.max(start + 1) - 1].position;
+
+                Stmt::Error {
+                    position: first.to(last),
+                }
+            }
+        }
+    }
+
+    //panic mode, skip tokens up to the end of the broken statement: past a `;`, in front
+    //of a `}` closing the enclosing block or in front of a keyword starting a statement;
+    //braces opened by the broken statement are skipped along with it
+    fn synchronize(&mut self, start: usize) {
+        if self.position == start {
+            self.skip();
+        }
+
+        let mut open = self.tokens[start..self.position]
+            .iter()
+            .map(|token| match &token.kind {
+                TokenKind::Punctuator(punctuator) if punctuator.0 == "{" => 1,
+                TokenKind::Punctuator(punctuator) if punctuator.0 == "}" => -1,
+                _ => 0,
+            })
+            .sum::<isize>()
+            .max(0);
+
+        while !self.at_end() {
+            let previous = &self.tokens[self.position - 1].kind;
+
+            let ended = matches!(
+                previous,
+                TokenKind::Punctuator(punctuator) if punctuator.0 == ";" || punctuator.0 == "}"
+            );
+
+            if open == 0 && ended {
+                return;
+            }
+
+            match &self.peek().kind {
+                TokenKind::Punctuator(punctuator) if punctuator.0 == "{" => open += 1,
+                TokenKind::Punctuator(punctuator) if punctuator.0 == "}" => {
+                    if open == 0 {
+                        //a stray `}` outside of any block would never be consumed otherwise
+                        if self.block_depth == 0 {
+                            self.skip();
+                        }
+                        return;
+                    }
+                    open -= 1;
+                }
+                TokenKind::Keyword(keyword) if open == 0 && starts_statement(&keyword.0) => return,
+                _ => {}
+            }
+
+            self.skip();
+        }
+    }
+
+    //step over a token of a broken statement, lexer errors do not depend on the syntax
+    //error being recovered from so they are still reported, unless that error is theirs
+    fn skip(&mut self) {
+        let token = self.advance();
+
+        if let TokenKind::Error(error) = token.kind {
+            let reported = self
+                .errors
+                .last()
+                .is_some_and(|last| last.position == token.position);
+
+            if !reported {
+                self.errors.push(ParseError::new(error, token.position));
+            }
+        }
+    }
+
+    fn parse_statement(&mut self) -> Result<Stmt, ParseError> {
+        //there is one table of types and methods for the whole program, so a declaration
+        //inside a block would change them for code outside of it; the declaration still
+        //parses fine, so the error is reported and parsing goes on
+        if self.block_depth > 0 {
+            if let TokenKind::Keyword(keyword) = &self.peek().kind {
+                if matches!(keyword.0.as_str(), "struct" | "enum" | "impl") {
+                    self.errors.push(ParseError::new(
+                        Error::new(
+                            ErrorType::UnexpectedToken,
+                            &format!("`{}` is only allowed at the top level", keyword.0),
+                            &keyword.0,
+                        ),
+                        self.peek().position.clone(),
+                    ));
+                }
+            }
+        }
+
+        match &self.peek().kind {
+            TokenKind::Keyword(keyword) if keyword.0 == "let" || keyword.0 == "const" => {
+                self.parse_declaration()
+            }
+            TokenKind::Keyword(keyword) if keyword.0 == "func" => self.parse_function(None),
+            TokenKind::Keyword(keyword) if keyword.0 == "struct" => self.parse_struct(),
+            TokenKind::Keyword(keyword) if keyword.0 == "enum" => self.parse_enum(),
+            TokenKind::Keyword(keyword) if keyword.0 == "impl" => self.parse_impl(),
+            TokenKind::Keyword(keyword) if keyword.0 == "return" => self.parse_return(),
+            TokenKind::Keyword(keyword) if keyword.0 == "if" => self.parse_if(),
+            TokenKind::Keyword(keyword) if keyword.0 == "while" => self.parse_while(),
+            TokenKind::Keyword(keyword) if keyword.0 == "for" => self.parse_for(),
+            TokenKind::Keyword(keyword) if keyword.0 == "break" || keyword.0 == "continue" => {
+                let keyword = self.advance();
+                let end = self.expect_punctuator(";")?;
+                let position = keyword.position.to(&end.position);
+
+                if keyword.kind == TokenKind::Keyword(Keyword::new("break")) {
+                    Ok(Stmt::Break { position })
+                } else {
+                    Ok(Stmt::Continue { position })
+                }
+            }
+            TokenKind::Punctuator(punctuator) if punctuator.0 == "{" => {
+                Ok(Stmt::Block(self.parse_block()?))
+            }
+            _ => {
+                let expr = self.parse_expression()?;
+
+                //a match used as a statement ends at its `}` like a block does
+                if matches!(expr.kind, ExprKind::Match { .. }) && !self.check_punctuator(";") {
+                    let position = expr.position.clone();
+                    return Ok(Stmt::Expr { expr, position });
+                }
+
+                let end = self.expect_punctuator(";")?;
+                let position = expr.position.to(&end.position);
+
+                Ok(Stmt::Expr { expr, position })
+            }
+        }
+    }
+
+    //if condition { ... } else if condition { ... } else { ... }
+    fn parse_if(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.advance();
+        let condition = self.parse_condition()?;
+        let then_branch = self.parse_block()?;
+        let mut position = keyword.position.to(&then_branch.position);
+
+        let else_branch = if self.check_keyword("else") {
+            self.advance();
+
+            let branch = if self.check_keyword("if") {
+                self.nested(Parser::parse_if)?
+            } else {
+                Stmt::Block(self.parse_block()?)
+            };
+
+            position = position.to(branch.position());
+            Some(Box::new(branch))
+        } else {
+            None
+        };
+
+        Ok(Stmt::If {
+            condition,
+            then_branch,
+            else_branch,
+            position,
+        })
+    }
+
+    fn parse_while(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.advance();
+        let condition = self.parse_condition()?;
+        let body = self.parse_block()?;
+        let position = keyword.position.to(&body.position);
+
+        Ok(Stmt::While {
+            condition,
+            body,
+            position,
+        })
+    }
+
+    //for (init; condition; update) { ... } or for name in iterable { ... }
+    fn parse_for(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.advance();
+
+        if !self.check_punctuator("(") {
+            let (name, name_position) = self.expect_name("Expected `(` or a loop variable")?;
+
+            self.expect_keyword("in")?;
+            let iterable = self.parse_condition()?;
+            let body = self.parse_block()?;
+            let position = keyword.position.to(&body.position);
+
+            return Ok(Stmt::ForIn {
+                name,
+                name_position,
+                iterable,
+                body,
+                position,
+            });
+        }
+
+        self.advance();
+
+        //the init is a full statement, so it brings its own `;`
+        let init = if self.check_punctuator(";") {
+            self.advance();
+            None
+        } else if self.check_keyword("let") {
+            Some(Box::new(self.parse_declaration()?))
+        } else {
+            let expr = self.parse_expression()?;
+            let end = self.expect_punctuator(";")?;
+            let position = expr.position.to(&end.position);
+
+            Some(Box::new(Stmt::Expr { expr, position }))
+        };
+
+        let condition = if self.check_punctuator(";") {
+            None
+        } else {
+            Some(self.parse_expression()?)
+        };
+        self.expect_punctuator(";")?;
+
+        let update = if self.check_punctuator(")") {
+            None
+        } else {
+            Some(self.parse_expression()?)
+        };
+        self.expect_punctuator(")")?;
+
+        let body = self.parse_block()?;
+        let position = keyword.position.to(&body.position);
+
+        Ok(Stmt::For {
+            init,
+            condition,
+            update,
+            body,
+            position,
+        })
+    }
+
+    //func name(a: int, b: float): float { ... }
+    //self_type is the struct a method belongs to, methods take self as their first parameter
+    fn parse_function(&mut self, self_type: Option<&str>) -> Result<Stmt, ParseError> {
+        let doc = self.take_doc();
+        let keyword = self.advance();
+
+        let (name, name_position) = self.expect_name("Expected a function name")?;
+
+        self.expect_punctuator("(")?;
+        let mut params = Vec::new();
+
+        if let Some(self_type) = self_type {
+            let position = match &self.peek().kind {
+                TokenKind::Identifier(Identifier(name)) if name == "self" => {
+                    self.advance().position
+                }
+                _ => return Err(self.error("Expected `self`")),
+            };
+
+            params.push(Param {
+                name: "self".to_string(),
+                ty: Type::Named(self_type.to_string()),
+                position,
+            });
+
+            if !self.check_punctuator(")") {
+                self.expect_punctuator(",")?;
+            }
+        }
+
+        while !self.check_punctuator(")") {
+            params.push(self.parse_param()?);
+
+            if !self.check_punctuator(")") {
+                self.expect_punctuator(",")?;
+            }
+        }
+        self.expect_punctuator(")")?;
+
+        let return_ty = self.parse_annotation()?;
+
+        let body = self.parse_block()?;
+        let position = keyword.position.to(&body.position);
+
+        Ok(Stmt::Func {
+            name,
+            name_position,
+            params,
+            return_ty,
+            body,
+            doc,
+            position,
+        })
+    }
+
+    //struct Name { field: type, ... }
+    fn parse_struct(&mut self) -> Result<Stmt, ParseError> {
+        let doc = self.take_doc();
+        let keyword = self.advance();
+
+        let (name, name_position) = self.expect_name("Expected a struct name")?;
+        self.expect_punctuator("{")?;
+
+        let mut fields = Vec::new();
+
+        while !self.check_punctuator("}") {
+            let (name, position) = self.expect_name("Expected a field name")?;
+            self.expect_punctuator(":")?;
+            let ty = self.parse_type()?;
+
+            fields.push(Field { name, ty, position });
+
+            if !self.check_punctuator("}") {
+                self.expect_punctuator(",")?;
+            }
+        }
+
+        let close = self.advance();
+
+        Ok(Stmt::Struct {
+            name,
+            name_position,
+            fields,
+            doc,
+            position: keyword.position.to(&close.position),
+        })
+    }
+
+    //enum Name { Variant, Variant(type, ...), ... }
+    fn parse_enum(&mut self) -> Result<Stmt, ParseError> {
+        let doc = self.take_doc();
+        let keyword = self.advance();
+
+        let (name, name_position) = self.expect_name("Expected an enum name")?;
+        self.expect_punctuator("{")?;
+
+        let mut variants = Vec::new();
+
+        while !self.check_punctuator("}") {
+            let (name, position) = self.expect_name("Expected a variant name")?;
+
+            let fields = if self.check_punctuator("(") {
+                self.advance();
+                let (fields, _) = self.parse_type_list()?;
+                fields
+            } else {
+                Vec::new()
+            };
+
+            variants.push(Variant {
+                name,
+                fields,
+                position,
+            });
+
+            if !self.check_punctuator("}") {
+                self.expect_punctuator(",")?;
+            }
+        }
+
+        let close = self.advance();
+
+        Ok(Stmt::Enum {
+            name,
+            name_position,
+            variants,
+            doc,
+            position: keyword.position.to(&close.position),
+        })
+    }
+
+    //impl Name { func method(self, ...) { ... } ... }
+    fn parse_impl(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.advance();
+
+        let (name, name_position) = self.expect_name("Expected a struct name")?;
+        self.expect_punctuator("{")?;
+
+        let mut methods = Vec::new();
+
+        while !self.check_punctuator("}") {
+            if !self.check_keyword("func") {
+                return Err(self.error("Expected a method"));
+            }
+
+            methods.push(self.parse_function(Some(&name))?);
+        }
+
+        let close = self.advance();
+
+        Ok(Stmt::Impl {
+            name,
+            name_position,
+            methods,
+            position: keyword.position.to(&close.position),
+        })
+    }
+
+    //parameters must carry a type, name: type
+    fn parse_param(&mut self) -> Result<Param, ParseError> {
+        let (name, position) = self.expect_name("Expected a parameter")?;
+
+        if !self.check_punctuator(":") {
+            return Err(self.error("Expected a parameter type"));
+        }
+        self.advance();
+
+        let ty = self.parse_type()?;
+
+        Ok(Param { name, ty, position })
+    }
+
+    //an optional `: type`, as used by declarations and return types
+    fn parse_annotation(&mut self) -> Result<Option<Type>, ParseError> {
+        if !self.check_punctuator(":") {
+            return Ok(None);
+        }
+        self.advance();
+
+        Ok(Some(self.parse_type()?))
+    }
+
+    //a type followed by any number of `?`, so int?? is an optional optional int
+    fn parse_type(&mut self) -> Result<Type, ParseError> {
+        let mut ty = self.nested(Parser::parse_type_primary)?;
+
+        while self.check_punctuator("?") {
+            self.advance();
+            ty = Type::Optional(Box::new(ty));
+        }
+
+        Ok(ty)
+    }
+
+    fn parse_type_primary(&mut self) -> Result<Type, ParseError> {
+        match &self.peek().kind {
+            //array<element>
+            TokenKind::Type(Type::Array(None)) => {
+                self.advance();
+
+                if !self.check_operator("<") {
+                    return Ok(Type::Array(None));
+                }
+                self.advance();
+
+                let element = self.parse_type()?;
+                self.expect_type_close()?;
+
+                Ok(Type::Array(Some(Box::new(element))))
+            }
+            TokenKind::Type(ty) => {
+                let ty = ty.clone();
+                self.advance();
+                Ok(ty)
+            }
+            //map is only special when it has type arguments, so it stays usable as a name
+            TokenKind::Identifier(Identifier(name))
+                if name == "map" && self.check_operator_at(1, "<") =>
+            {
+                self.advance();
+                self.advance();
+
+                let key = self.parse_type()?;
+                self.expect_punctuator(",")?;
+                let value = self.parse_type()?;
+                self.expect_type_close()?;
+
+                Ok(Type::Map(Box::new(key), Box::new(value)))
+            }
+            TokenKind::Identifier(Identifier(name)) => {
+                let ty = Type::Named(name.clone());
+                self.advance();
+                Ok(ty)
+            }
+            //func(params): return
+            TokenKind::Keyword(keyword) if keyword.0 == "func" => {
+                self.advance();
+                self.expect_punctuator("(")?;
+
+                let (params, _) = self.parse_type_list()?;
+                let ret = self.parse_annotation()?;
+
+                Ok(Type::Func(params, ret.map(Box::new)))
+            }
+            //(int, string) is a tuple, (int) only groups and (int,) is a tuple of one
+            TokenKind::Punctuator(punctuator) if punctuator.0 == "(" => {
+                self.advance();
+
+                let (mut elements, trailing_comma) = self.parse_type_list()?;
+
+                if elements.len() == 1 && !trailing_comma {
+                    return Ok(elements.remove(0));
+                }
+
+                Ok(Type::Tuple(elements))
+            }
+            _ => Err(self.error("Expected a type")),
+        }
+    }
+
+    //the `>` closing type arguments, in `array<int>= [1]` the lexer reads `>=` as one operator
+    //so it is split in two and the `=` is left for the caller
+    fn expect_type_close(&mut self) -> Result<Token, ParseError> {
+        if !self.check_operator(">=") {
+            return self.expect_operator(">");
+        }
+
+        let token = self.tokens[self.position].clone();
+        let (start, end) = (token.position.span.start_byte, token.position.span.end_byte);
+        let column = token.position.start_column;
+
+        let close = Token {
+            kind: TokenKind::Operator(Operator::new(">")),
+            position: Position::new(token.position.line, column, column + 1)
+                .with_span(token.position.span),
+            leading_trivia: token.leading_trivia,
+            trailing_trivia: Vec::new(),
+        }
+        .with_bytes(start, start + 1);
+
+        self.tokens[self.position] = Token {
+            kind: TokenKind::Punctuator(Punctuator::new("=")),
+            position: Position::new(token.position.line, column + 1, column + 2)
+                .with_span(token.position.span),
+            leading_trivia: Vec::new(),
+            trailing_trivia: token.trailing_trivia,
+        }
+        .with_bytes(start + 1, end);
+
+        Ok(close)
+    }
+
+    //comma separated types up to and including the closing paren, also returns whether
+    //the list ended in a trailing comma
+    fn parse_type_list(&mut self) -> Result<(Vec<Type>, bool), ParseError> {
+        let mut types = Vec::new();
+        let mut trailing_comma = false;
+
+        while !self.check_punctuator(")") {
+            types.push(self.parse_type()?);
+            trailing_comma = false;
+
+            if !self.check_punctuator(")") {
+                self.expect_punctuator(",")?;
+                trailing_comma = true;
+            }
+        }
+        self.advance();
+
+        Ok((types, trailing_comma))
+    }
+
+    fn parse_block(&mut self) -> Result<Block, ParseError> {
+        let open = self.expect_punctuator("{")?;
+        let mut statements = Vec::new();
+
+        self.block_depth += 1;
+
+        while !self.check_punctuator("}") {
+            if self.at_end() {
+                self.block_depth -= 1;
+                return Err(self.error("Expected `}`"));
+            }
+
+            statements.push(self.parse_statement_recovering());
+        }
+
+        self.block_depth -= 1;
+        let close = self.advance();
+
+        Ok(Block {
+            statements,
+            position: open.position.to(&close.position),
+        })
+    }
+
+    //return; or return value;
+    fn parse_return(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.advance();
+
+        let value = if self.check_punctuator(";") {
+            None
+        } else {
+            Some(self.parse_expression()?)
+        };
+
+        let end = self.expect_punctuator(";")?;
+
+        Ok(Stmt::Return {
+            value,
+            position: keyword.position.to(&end.position),
+        })
+    }
+
+    //let name: type = init; and const name: type = init;
+    fn parse_declaration(&mut self) -> Result<Stmt, ParseError> {
+        let doc = self.take_doc();
+        let keyword = self.advance();
+        let is_const = keyword.kind == TokenKind::Keyword(Keyword::new("const"));
+
+        let (name, name_position) = self.expect_name("Expected an identifier")?;
+        let ty = self.parse_annotation()?;
+
+        self.expect_punctuator("=")?;
+        let init = self.parse_expression()?;
+        let end = self.expect_punctuator(";")?;
+        let position = keyword.position.to(&end.position);
+
+        if is_const {
+            Ok(Stmt::Const {
+                name,
+                name_position,
+                ty,
+                init,
+                doc,
+                position,
+            })
+        } else {
+            Ok(Stmt::Let {
+                name,
+                name_position,
+                ty,
+                init,
+                doc,
+                position,
+            })
+        }
+    }
+
+    fn parse_expression(&mut self) -> Result<Expr, ParseError> {
+        self.nested(Parser::parse_assignment)
+    }
+
+    //a binary expression, or an assignment to it when an `=` follows
+    fn parse_assignment(&mut self) -> Result<Expr, ParseError> {
+        let expr = self.parse_binary(1)?;
+
+        if !self.check_punctuator("=") {
+            return Ok(expr);
+        }
+
+        //the assignment still parses fine, so the error is reported and parsing goes on
+        if !matches!(
+            expr.kind,
+            ExprKind::Identifier(_)
+                | ExprKind::Index { .. }
+                | ExprKind::Field { .. }
+                | ExprKind::Error
+        ) {
+            self.errors.push(ParseError::new(
+                Error::new(
+                    ErrorType::UnexpectedToken,
+                    "Invalid assignment target",
+                    &describe_expr(&expr),
+                ),
+                expr.position.clone(),
+            ));
+        }
+
+        //assignment binds loosest and is right associative, so a = b = 1 is a = (b = 1)
+        self.advance();
+        let value = self.parse_expression()?;
+        let position = expr.position.to(&value.position);
+
+        Ok(Expr::new(
+            ExprKind::Assign {
+                target: Box::new(expr),
+                value: Box::new(value),
+            },
+            position,
+        ))
+    }
+
+    //precedence climbing over the binary operators, only operators that bind at least as
+    //tight as min_precedence are taken so lower ones are left to the caller
+    fn parse_binary(&mut self, min_precedence: u8) -> Result<Expr, ParseError> {
+        let mut left = self.parse_unary()?;
+
+        while let Some(op) = self.peek_binary_operator() {
+            let precedence = precedence(op);
+
+            if precedence < min_precedence {
+                break;
+            }
+
+            self.advance();
+
+            //all binary operators are left associative, so the right side only takes tighter ones
+            let right = self.parse_binary(precedence + 1)?;
+            let position = left.position.to(&right.position);
+
+            left = Expr::new(
+                ExprKind::Binary {
+                    op,
+                    left: Box::new(left),
+                    right: Box::new(right),
+                },
+                position,
+            );
+        }
+
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, ParseError> {
+        let op = match &self.peek().kind {
+            TokenKind::Operator(operator) => UnaryOp::from_operator(&operator.0),
+            _ => None,
+        };
+
+        match op {
+            Some(op) => {
+                let token = self.advance();
+                let operand = self.nested(Parser::parse_unary)?;
+                let position = token.position.to(&operand.position);
+
+                Ok(Expr::new(
+                    ExprKind::Unary {
+                        op,
+                        operand: Box::new(operand),
+                    },
+                    position,
+                ))
+            }
+            None => self.parse_postfix(),
+        }
+    }
+
+    fn parse_postfix(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.parse_primary()?;
+
+        loop {
+            if self.check_punctuator("(") {
+                expr = self.parse_call(expr)?;
+                continue;
+            }
+
+            if self.check_punctuator("[") {
+                expr = self.parse_index(expr)?;
+                continue;
+            }
+
+            if self.check_punctuator(".") {
+                self.advance();
+
+                let (name, name_position) = self.expect_name("Expected a field name")?;
+                let position = expr.position.to(&name_position);
+
+                expr = Expr::new(
+                    ExprKind::Field {
+                        target: Box::new(expr),
+                        name,
+                    },
+                    position,
+                );
+                continue;
+            }
+
+            let op = match &self.peek().kind {
+                TokenKind::Operator(operator) => PostfixOp::from_operator(&operator.0),
+                _ => None,
+            };
+
+            let Some(op) = op else {
+                break;
+            };
+
+            let token = self.advance();
+            let position = expr.position.to(&token.position);
+
+            expr = Expr::new(
+                ExprKind::Postfix {
+                    op,
+                    operand: Box::new(expr),
+                },
+                position,
+            );
+        }
+
+        Ok(expr)
+    }
+
+    //callee(arg, arg)
+    fn parse_call(&mut self, callee: Expr) -> Result<Expr, ParseError> {
+        self.expect_punctuator("(")?;
+        let (args, close) = self.parse_list(")")?;
+        let position = callee.position.to(&close.position);
+
+        Ok(Expr::new(
+            ExprKind::Call {
+                callee: Box::new(callee),
+                args,
+            },
+            position,
+        ))
+    }
+
+    //target[index]
+    fn parse_index(&mut self, target: Expr) -> Result<Expr, ParseError> {
+        self.expect_punctuator("[")?;
+        let index = self.parse_expression()?;
+        let close = self.expect_punctuator("]")?;
+        let position = target.position.to(&close.position);
+
+        Ok(Expr::new(
+            ExprKind::Index {
+                target: Box::new(target),
+                index: Box::new(index),
+            },
+            position,
+        ))
+    }
+
+    //comma separated expressions up to and including the closing punctuator,
+    //a trailing comma is allowed so lists can be written one element per line
+    fn parse_list(&mut self, close: &str) -> Result<(Vec<Expr>, Token), ParseError> {
+        let mut elements = Vec::new();
+
+        while !self.check_punctuator(close) {
+            elements.push(self.with_struct_literals(true, Parser::parse_expression)?);
+
+            if !self.check_punctuator(close) {
+                self.expect_punctuator(",")?;
+            }
+        }
+
+        Ok((elements, self.advance()))
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, ParseError> {
+        //a parenthesized expression only changes grouping, the node gets the span of the parens
+        if self.check_punctuator("(") {
+            let open = self.advance();
+            let expr = self.with_struct_literals(true, Parser::parse_expression)?;
+            let close = self.expect_punctuator(")")?;
+
+            return Ok(Expr::new(expr.kind, open.position.to(&close.position)));
+        }
+
+        if self.check_punctuator("[") {
+            let open = self.advance();
+            let (elements, close) = self.parse_list("]")?;
+
+            return Ok(Expr::new(
+                ExprKind::Array(elements),
+                open.position.to(&close.position),
+            ));
+        }
+
+        if matches!(self.peek().kind, TokenKind::TemplateHead(_)) {
+            return self.parse_interpolation();
+        }
+
+        if self.check_keyword("match") {
+            return self.parse_match();
+        }
+
+        if matches!(self.peek().kind, TokenKind::Identifier(_)) && self.check_punctuator_at(1, "::")
+        {
+            return self.parse_variant();
+        }
+
+        if self.allow_struct_literal
+            && matches!(self.peek().kind, TokenKind::Identifier(_))
+            && self.check_punctuator_at(1, "{")
+        {
+            return self.parse_struct_literal();
+        }
+
+        let kind = match &self.peek().kind {
+            TokenKind::IntLiteral(value) => ExprKind::Int(value.0),
+            TokenKind::FloatLiteral(value) => ExprKind::Float(value.0),
+            TokenKind::StringLiteral(value) => ExprKind::String(value.0.clone()),
+            TokenKind::BoolLiteral(value) => ExprKind::Bool(value.0),
+            TokenKind::Identifier(identifier) => ExprKind::Identifier(identifier.0.clone()),
+            //a missing operand in front of a token that may follow an expression is
+            //reported and left as a hole, so `f(1, )` or `let x = ;` do not end the statement
+            TokenKind::Punctuator(punctuator)
+                if [";", ",", ")", "]"].contains(&punctuator.0.as_str()) =>
+            {
+                self.errors.push(self.error("Expected an expression"));
+
+                let start = &self.peek().position;
+                let span = Span::new(
+                    start.span.file,
+                    start.span.start_byte,
+                    start.span.start_byte,
+                );
+                return Ok(Expr::new(
+                    ExprKind::Error,
+                    Position::new(start.line, start.start_column, start.start_column)
+                        .with_span(span),
+                ));
+            }
+            _ => return Err(self.error("Expected an expression")),
+        };
+
+        let token = self.advance();
+        Ok(Expr::new(kind, token.position))
+    }
+
+    //Name { field: value, ... }
+    fn parse_struct_literal(&mut self) -> Result<Expr, ParseError> {
+        let (name, name_position) = self.expect_name("Expected a struct name")?;
+        self.expect_punctuator("{")?;
+
+        let mut fields = Vec::new();
+
+        while !self.check_punctuator("}") {
+            let (name, position) = self.expect_name("Expected a field name")?;
+            self.expect_punctuator(":")?;
+            let value = self.with_struct_literals(true, Parser::parse_expression)?;
+
+            fields.push(FieldInit {
+                name,
+                value,
+                position,
+            });
+
+            if !self.check_punctuator("}") {
+                self.expect_punctuator(",")?;
+            }
+        }
+
+        let close = self.advance();
+
+        Ok(Expr::new(
+            ExprKind::StructLiteral { name, fields },
+            name_position.to(&close.position),
+        ))
+    }
+
+    //Enum::Variant or Enum::Variant(value, ...)
+    fn parse_variant(&mut self) -> Result<Expr, ParseError> {
+        let (enum_name, position) = self.expect_name("Expected an enum name")?;
+        self.expect_punctuator("::")?;
+        let (variant, mut end) = self.expect_name("Expected a variant name")?;
+
+        let mut args = Vec::new();
+
+        if self.check_punctuator("(") {
+            self.advance();
+            let (values, close) = self.parse_list(")")?;
+
+            args = values;
+            end = close.position;
+        }
+
+        Ok(Expr::new(
+            ExprKind::Variant {
+                enum_name,
+                variant,
+                args,
+            },
+            position.to(&end),
+        ))
+    }
+
+    //match subject { pattern => value, pattern if guard => value, ... }
+    fn parse_match(&mut self) -> Result<Expr, ParseError> {
+        let keyword = self.advance();
+        let subject = self.parse_condition()?;
+        self.expect_punctuator("{")?;
+
+        let mut arms = Vec::new();
+
+        while !self.check_punctuator("}") {
+            let pattern = self.parse_pattern()?;
+
+            let guard = if self.check_keyword("if") {
+                self.advance();
+                Some(self.with_struct_literals(true, Parser::parse_expression)?)
+            } else {
+                None
+            };
+
+            self.expect_punctuator("=>")?;
+            let body = self.with_struct_literals(true, Parser::parse_expression)?;
+            let position = pattern.position.to(&body.position);
+
+            arms.push(MatchArm {
+                pattern,
+                guard,
+                body,
+                position,
+            });
+
+            if !self.check_punctuator("}") {
+                self.expect_punctuator(",")?;
+            }
+        }
+
+        let close = self.advance();
+
+        Ok(Expr::new(
+            ExprKind::Match {
+                subject: Box::new(subject),
+                arms,
+            },
+            keyword.position.to(&close.position),
+        ))
+    }
+
+    fn parse_pattern(&mut self) -> Result<Pattern, ParseError> {
+        //a minus sign only belongs to a pattern as part of a negative number
+        if self.check_operator("-") {
+            let minus = self.advance();
+
+            let kind = match &self.peek().kind {
+                TokenKind::IntLiteral(value) => ExprKind::Int(-value.0),
+                TokenKind::FloatLiteral(value) => ExprKind::Float(-value.0),
+                _ => return Err(self.error("Expected a number")),
+            };
+
+            let position = minus.position.to(&self.advance().position);
+
+            return Ok(Pattern {
+                kind: PatternKind::Literal(Expr::new(kind, position.clone())),
+                position,
+            });
+        }
+
+        let kind = match &self.peek().kind {
+            TokenKind::IntLiteral(value) => ExprKind::Int(value.0),
+            TokenKind::FloatLiteral(value) => ExprKind::Float(value.0),
+            TokenKind::StringLiteral(value) => ExprKind::String(value.0.clone()),
+            TokenKind::BoolLiteral(value) => ExprKind::Bool(value.0),
+            TokenKind::Identifier(_) if self.check_punctuator_at(1, "::") => {
+                return self.parse_variant_pattern();
+            }
+            TokenKind::Identifier(Identifier(name)) => {
+                let kind = if name == "_" {
+                    PatternKind::Wildcard
+                } else {
+                    PatternKind::Binding(name.clone())
+                };
+
+                let position = self.advance().position;
+                return Ok(Pattern { kind, position });
+            }
+            _ => return Err(self.error("Expected a pattern")),
+        };
+
+        let position = self.advance().position;
+
+        Ok(Pattern {
+            kind: PatternKind::Literal(Expr::new(kind, position.clone())),
+            position,
+        })
+    }
+
+    //Enum::Variant or Enum::Variant(pattern, ...)
+    fn parse_variant_pattern(&mut self) -> Result<Pattern, ParseError> {
+        let (enum_name, position) = self.expect_name("Expected an enum name")?;
+        self.expect_punctuator("::")?;
+        let (variant, mut end) = self.expect_name("Expected a variant name")?;
+
+        let mut fields = Vec::new();
+
+        if self.check_punctuator("(") {
+            self.advance();
+
+            while !self.check_punctuator(")") {
+                fields.push(self.nested(Parser::parse_pattern)?);
+
+                if !self.check_punctuator(")") {
+                    self.expect_punctuator(",")?;
+                }
+            }
+
+            end = self.advance().position;
+        }
+
+        Ok(Pattern {
+            kind: PatternKind::Variant {
+                enum_name,
+                variant,
+                fields,
+            },
+            position: position.to(&end),
+        })
+    }
+
+    //an if/while condition or for-in iterable, the `{` after it always opens the body
+    fn parse_condition(&mut self) -> Result<Expr, ParseError> {
+        self.with_struct_literals(false, Parser::parse_expression)
+    }
+
+    //run parse one level deeper, input nested past MAX_DEPTH is an error
+    fn nested<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<T, ParseError> {
+        if self.depth == MAX_DEPTH {
+            let token = self.peek();
+            let error = Error::new(
+                ErrorType::StackOverflow,
+                "Too deeply nested",
+                &describe(&token.kind),
+            );
+
+            return Err(ParseError::new(error, token.position.clone()));
+        }
+
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+
+        result
+    }
+
+    fn with_struct_literals(
+        &mut self,
+        allow: bool,
+        parse: impl FnOnce(&mut Self) -> Result<Expr, ParseError>,
+    ) -> Result<Expr, ParseError> {
+        let saved = std::mem::replace(&mut self.allow_struct_literal, allow);
+        let result = parse(self);
+        self.allow_struct_literal = saved;
+
+        result
+    }
+
+    //"a ${x} b" arrives as TemplateHead("a ") x TemplateTail(" b")
+    fn parse_interpolation(&mut self) -> Result<Expr, ParseError> {
+        let head = self.advance();
+        let mut parts = Vec::new();
+        Self::push_template_text(&mut parts, &head);
+
+        loop {
+            parts.push(self.with_struct_literals(true, Parser::parse_expression)?);
+
+            match self.peek().kind {
+                TokenKind::TemplateMiddle(_) => {
+                    let middle = self.advance();
+                    Self::push_template_text(&mut parts, &middle);
+                }
+                TokenKind::TemplateTail(_) => {
+                    let tail = self.advance();
+                    Self::push_template_text(&mut parts, &tail);
+
+                    return Ok(Expr::new(
+                        ExprKind::Interpolation(parts),
+                        head.position.to(&tail.position),
+                    ));
+                }
+                _ => return Err(self.error("Expected `}`")),
+            }
+        }
+    }
+
+    //the text of a template part as a string expression, empty text adds nothing
+    fn push_template_text(parts: &mut Vec<Expr>, token: &Token) {
+        match &token.kind {
+            TokenKind::TemplateHead(text)
+            | TokenKind::TemplateMiddle(text)
+            | TokenKind::TemplateTail(text)
+                if !text.0.is_empty() =>
+            {
+                parts.push(Expr::new(
+                    ExprKind::String(text.0.clone()),
+                    token.position.clone(),
+                ));
+            }
+            _ => {}
+        }
+    }
+
+    fn peek_binary_operator(&self) -> Option<BinaryOp> {
+        match &self.peek().kind {
+            TokenKind::Operator(operator) => BinaryOp::from_operator(&operator.0),
+            _ => None,
+        }
+    }
+
+    fn expect_name(&mut self, message: &str) -> Result<(String, Position), ParseError> {
+        match &self.peek().kind {
+            TokenKind::Identifier(Identifier(name)) => {
+                let name = name.clone();
+                Ok((name, self.advance().position))
+            }
+            _ => Err(self.error(message)),
+        }
+    }
+
+    //the doc comment written right above the current token, if any
+    fn take_doc(&mut self) -> Option<String> {
+        self.docs.remove(&self.position)
+    }
+
+    fn check_keyword(&self, value: &str) -> bool {
+        matches!(&self.peek().kind, TokenKind::Keyword(keyword) if keyword.0 == value)
+    }
+
+    fn expect_keyword(&mut self, value: &str) -> Result<Token, ParseError> {
+        if self.check_keyword(value) {
+            Ok(self.advance())
+        } else {
+            Err(self.error(&format!("Expected `{}`", value)))
+        }
+    }
+
+    fn check_operator(&self, value: &str) -> bool {
+        self.check_operator_at(0, value)
+    }
+
+    //look ahead without consuming, offset 0 is the current token
+    fn check_operator_at(&self, offset: usize, value: &str) -> bool {
+        matches!(&self.peek_at(offset).kind, TokenKind::Operator(operator) if operator.0 == value)
+    }
+
+    fn check_punctuator_at(&self, offset: usize, value: &str) -> bool {
+        matches!(&self.peek_at(offset).kind, TokenKind::Punctuator(punctuator) if punctuator.0 == value)
+    }
+
+    fn expect_operator(&mut self, value: &str) -> Result<Token, ParseError> {
+        if self.check_operator(value) {
+            Ok(self.advance())
+        } else {
+            Err(self.error(&format!("Expected `{}`", value)))
+        }
+    }
+
+    fn check_punctuator(&self, value: &str) -> bool {
+        matches!(&self.peek().kind, TokenKind::Punctuator(punctuator) if punctuator.0 == value)
+    }
+
+    fn expect_punctuator(&mut self, value: &str) -> Result<Token, ParseError> {
+        if self.check_punctuator(value) {
+            Ok(self.advance())
+        } else {
+            Err(self.error(&format!("Expected `{}`", value)))
+        }
+    }
+
+    //build an error pointing at the current token
+    fn error(&self, message: &str) -> ParseError {
+        let token = self.peek();
+
+        let error = match &token.kind {
+            //the lexer already explained what is wrong with this token
+            TokenKind::Error(error) => error.clone(),
+            TokenKind::Eof => Error::new(
+                ErrorType::UnexpectedEndOfInput,
+                &format!("{}, found end of input", message),
+                "end of input",
+            ),
+            kind => {
+                let found = describe(kind);
+                Error::new(
+                    ErrorType::UnexpectedToken,
+                    &format!("{}, found {}", message, found),
+                    &found,
+                )
+            }
+        };
+
+        ParseError::new(error, token.position.clone())
+    }
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.position]
+    }
+
+    //look ahead without consuming, offset 0 is the current token, reads past the end stop at eof
+    fn peek_at(&self, offset: usize) -> &Token {
+        let index = (self.position + offset).min(self.tokens.len() - 1);
+        &self.tokens[index]
+    }
+
+    fn advance(&mut self) -> Token {
+        let token = self.tokens[self.position].clone();
+
+        //never move past the eof token
+        if !self.at_end() {
+            self.position += 1;
+        }
+
+        token
+    }
+
+    fn at_end(&self) -> bool {
+        self.peek().kind == TokenKind::Eof
+    }
+}
+
+//the keywords error recovery stops in front of, all but the ones that continue a statement
+//or start an expression
+fn starts_statement(keyword: &str) -> bool {
+    KEYWORDS.contains(&keyword) && !matches!(keyword, "else" | "in" | "match")
+}
+
+//binary operator precedence, higher binds tighter, all of them are left associative
+//
+//  6  * / %
+//  5  + -
+//  4  < > <= >=
+//  3  == !=
+//  2  &&
+//  1  ||
+//
+//prefix operators (- ! ++ --) bind tighter than any binary operator and postfix ++ and --
+//tighter still, so `-x++ * 2` is `(-(x++)) * 2`
+fn precedence(op: BinaryOp) -> u8 {
+    match op {
+        BinaryOp::Or => 1,
+        BinaryOp::And => 2,
+        BinaryOp::Eq | BinaryOp::NotEq => 3,
+        BinaryOp::Less | BinaryOp::Greater | BinaryOp::LessEq | BinaryOp::GreaterEq => 4,
+        BinaryOp::Add | BinaryOp::Sub => 5,
+        BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem => 6,
+    }
+}
+
+//a short human readable name for an expression, used in error messages
+fn describe_expr(expr: &Expr) -> String {
+    match &expr.kind {
+        ExprKind::Int(_) | ExprKind::Float(_) | ExprKind::String(_) | ExprKind::Bool(_) => {
+            "literal".to_string()
+        }
+        ExprKind::Array(_) => "array literal".to_string(),
+        ExprKind::Interpolation(_) => "string".to_string(),
+        ExprKind::Identifier(name) => format!("identifier `{}`", name),
+        ExprKind::Call { .. } => "call".to_string(),
+        ExprKind::Index { .. } => "index".to_string(),
+        ExprKind::StructLiteral { name, .. } => format!("`{}` literal", name),
+        ExprKind::Field { name, .. } => format!("field `{}`", name),
+        ExprKind::Variant { variant, .. } => format!("variant `{}`", variant),
+        ExprKind::Match { .. } => "match".to_string(),
+        ExprKind::Assign { .. } => "assignment".to_string(),
+        ExprKind::Error => "error".to_string(),
+        ExprKind::Unary { .. } | ExprKind::Postfix { .. } | ExprKind::Binary { .. } => {
+            "expression".to_string()
+        }
+    }
+}
+
+//a short human readable name for a token, used in error messages
+fn describe(kind: &TokenKind) -> String {
+    match kind {
+        TokenKind::Operator(operator) => format!("operator `{}`", operator.0),
+        TokenKind::Punctuator(punctuator) => format!("`{}`", punctuator.0),
+        TokenKind::BoolLiteral(value) => format!("`{}`", value.0),
+        TokenKind::FloatLiteral(value) => format!("`{}`", value.0),
+        TokenKind::IntLiteral(value) => format!("`{}`", value.0),
+        TokenKind::StringLiteral(value) => format!("string \"{}\"", value.0),
+        TokenKind::TemplateHead(_) => "string".to_string(),
+        TokenKind::TemplateMiddle(_) | TokenKind::TemplateTail(_) => "`}`".to_string(),
+        TokenKind::Identifier(identifier) => format!("identifier `{}`", identifier.0),
+        TokenKind::Type(type_) => format!("type `{}`", type_),
+        TokenKind::Keyword(keyword) => format!("keyword `{}`", keyword.0),
+        TokenKind::DocComment(_) => "doc comment".to_string(),
+        TokenKind::Error(_) => "invalid token".to_string(),
+        TokenKind::Eof => "end of input".to_string(),
+    }
+}
@@ -0,0 +1,252 @@
+use std::ops::Range;
+
+use crate::Position;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, Token, Tokenizer};
+
+    #[test]
+    fn files_get_their_own_ids() {
+        let mut sources = SourceMap::new();
+        let main = sources.add("main.bs", "let x = 1;");
+        let lib = sources.add("lib.bs", "func f() { }");
+
+        assert_eq!(main, FileId(0));
+        assert_eq!(lib, FileId(1));
+        assert_eq!(sources.name(lib), "lib.bs");
+        assert_eq!(sources.source(main), "let x = 1;");
+        assert_eq!(sources.slice(Span::new(lib, 5, 6)), "f");
+    }
+
+    #[test]
+    fn locations() {
+        let mut sources = SourceMap::new();
+        let file = sources.add("main.bs", "let a = 1;\n\"é𝄞\" + b\r\nc");
+
+        assert_eq!(
+            sources.location(file, 0),
+            Location {
+                line: 1,
+                column: 1,
+                utf8_column: 1,
+                utf16_column: 1
+            }
+        );
+
+        //b comes after a 2 byte and a 4 byte char, the second is a surrogate pair in utf-16
+        assert_eq!(
+            sources.location(file, 22),
+            Location {
+                line: 2,
+                column: 8,
+                utf8_column: 12,
+                utf16_column: 9
+            }
+        );
+        assert_eq!(sources.location(file, 25).line, 3);
+        assert_eq!(sources.location(file, 100).column, 2);
+        //byte 13 is the second byte of é
+        assert_eq!(sources.location(file, 13), sources.location(file, 12));
+
+        let (start, end) = sources.locations(Span::new(file, 4, 14));
+
+        assert_eq!((start.line, start.column), (1, 5));
+        assert_eq!((end.line, end.column), (2, 3));
+    }
+
+    #[test]
+    fn token_spans_slice_the_source() {
+        let mut sources = SourceMap::new();
+        sources.add("main.bs", "");
+        let file = sources.add("lib.bs", "let s = \"a\nb\";\nlet é = 1;");
+
+        let tokens = Tokenizer::with_file(sources.source(file), file).collect::<Vec<Token>>();
+        let text = tokens
+            .iter()
+            .map(|token| sources.slice(token.position.span))
+            .collect::<Vec<&str>>();
+
+        assert_eq!(
+            text,
+            ["let", "s", "=", "\"a\nb\"", ";", "let", "é", "=", "1", ";", ""]
+        );
+        assert_eq!(tokens[3].position.span, Span::new(file, 8, 13));
+        assert_eq!(
+            sources.position(tokens[6].position.span),
+            Position::new(3, 5, 6).with_span(Span::new(file, 19, 21))
+        );
+    }
+
+    #[test]
+    fn node_spans_cover_their_children() {
+        let source = "func f() {\n  return 1 +\n    2;\n}";
+        let program = parse(source).unwrap();
+
+        let span = program.statements[0].position().span;
+
+        assert_eq!(span, Span::new(FileId(0), 0, source.len()));
+        assert_eq!(&source[span.range()], source);
+    }
+}
+
+//an id handed out by a SourceMap, tokenizers that are not given one use FileId(0)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct FileId(pub usize);
+
+//a range of bytes in one file, start inclusive and end exclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Span {
+    pub file: FileId,
+    pub start_byte: usize,
+    pub end_byte: usize,
+}
+
+impl Span {
+    pub fn new(file: FileId, start_byte: usize, end_byte: usize) -> Self {
+        Span {
+            file,
+            start_byte,
+            end_byte,
+        }
+    }
+
+    //the span from the start of self to the end of other, unlike Position::to this works
+    //across lines; spans from another file leave self as it is
+    pub fn to(&self, other: &Span) -> Span {
+        if other.file != self.file {
+            return *self;
+        }
+
+        Span::new(
+            self.file,
+            self.start_byte.min(other.start_byte),
+            self.end_byte.max(other.end_byte),
+        )
+    }
+
+    pub fn len(&self) -> usize {
+        self.end_byte - self.start_byte
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start_byte == self.end_byte
+    }
+
+    pub fn range(&self) -> Range<usize> {
+        self.start_byte..self.end_byte
+    }
+}
+
+//a place in a file as an editor shows it, all numbers start at 1; column counts chars
+//like Position does, utf8_column counts bytes and utf16_column utf-16 code units, which
+//is what the language server protocol uses by default
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+    pub utf8_column: usize,
+    pub utf16_column: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct SourceFile {
+    name: String,
+    source: String,
+    //byte offset of the start of every line, the first is always 0
+    line_starts: Vec<usize>,
+}
+
+//every file of a program, so a span can be traced back to its file and its text
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        SourceMap::default()
+    }
+
+    pub fn add(&mut self, name: &str, source: &str) -> FileId {
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+
+        self.files.push(SourceFile {
+            name: name.to_string(),
+            source: source.to_string(),
+            line_starts,
+        });
+
+        FileId(self.files.len() - 1)
+    }
+
+    pub fn name(&self, file: FileId) -> &str {
+        &self.files[file.0].name
+    }
+
+    pub fn source(&self, file: FileId) -> &str {
+        &self.files[file.0].source
+    }
+
+    //the text a span covers
+    pub fn slice(&self, span: Span) -> &str {
+        &self.source(span.file)[span.range()]
+    }
+
+    //the line and columns of a byte offset, offsets past the end are taken as the end and
+    //offsets inside a char as the start of that char
+    pub fn location(&self, file: FileId, byte: usize) -> Location {
+        let file = &self.files[file.0];
+        let byte = char_boundary(&file.source, byte);
+
+        //the last line starting at or before the byte
+        let line = file.line_starts.partition_point(|&start| start <= byte);
+        let before = &file.source[file.line_starts[line - 1]..byte];
+
+        Location {
+            line,
+            column: before.chars().count() + 1,
+            utf8_column: before.len() + 1,
+            utf16_column: before.encode_utf16().count() + 1,
+        }
+    }
+
+    pub fn locations(&self, span: Span) -> (Location, Location) {
+        (
+            self.location(span.file, span.start_byte),
+            self.location(span.file, span.end_byte),
+        )
+    }
+
+    //the span as a Position, which only covers one line so a span ending on a later line
+    //is cut off at the end of its first line
+    pub fn position(&self, span: Span) -> Position {
+        let (start, end) = self.locations(span);
+
+        let end_column = if end.line == start.line {
+            end.column
+        } else {
+            let source = self.source(span.file);
+            let rest = &source[char_boundary(source, span.start_byte)..];
+            let line = rest.split('\n').next().unwrap_or("");
+
+            start.column + line.trim_end_matches('\r').chars().count()
+        };
+
+        Position::new(start.line, start.column, end_column).with_span(span)
+    }
+}
+
+//the last char boundary at or before byte, clamped to the end of source
+fn char_boundary(source: &str, byte: usize) -> usize {
+    let mut byte = byte.min(source.len());
+
+    while !source.is_char_boundary(byte) {
+        byte -= 1;
+    }
+
+    byte
+}
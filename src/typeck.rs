@@ -0,0 +1,2011 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::ast::{
+    BinaryOp, Block, Expr, ExprKind, FieldInit, MatchArm, Param, Pattern, PatternKind, Program,
+    Stmt, UnaryOp,
+};
+use crate::interpreter::Builtin;
+use crate::{Error, ErrorType, Position, Type};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    fn errors(source: &str) -> Vec<TypeError> {
+        check(&parse(source).unwrap())
+    }
+
+    fn messages(source: &str) -> Vec<String> {
+        errors(source)
+            .into_iter()
+            .map(|error| error.error.message)
+            .collect()
+    }
+
+    #[test]
+    fn well_typed_program() {
+        let source = "
+let x: float = 10 + 20.1;
+let y: int = 30 + 40;
+let s: string = \"a\" + \"b\";
+let r: bool = x > 1 && y != 2 || !true;
+let a: array = [1, 2, 3.4];
+func add(a: int, b: float): float {
+    return a + b;
+}
+let z: float = add(y, x);
+for (let i = 0; i < len(a); i++) {
+    if i % 2 == 0 { continue; }
+    print(s, i);
+}";
+
+        assert_eq!(errors(source), vec![]);
+    }
+
+    #[test]
+    fn initializer_mismatch() {
+        assert_eq!(
+            errors("let x: int = 1;\nlet y: int = \"hello\";"),
+            vec![TypeError::new(
+                Error::new(ErrorType::TypeMismatch, "Expected int, found string", "y"),
+                Position::new(2, 1, 22).with_bytes(16, 37),
+            )]
+        );
+        assert_eq!(
+            messages("const flag: bool = 1.5;"),
+            ["Expected bool, found float"]
+        );
+    }
+
+    #[test]
+    fn ints_widen_to_floats() {
+        assert_eq!(errors("let x: float = 1; x = 2;"), vec![]);
+        assert_eq!(messages("let x: int = 1.0;"), ["Expected int, found float"]);
+    }
+
+    #[test]
+    fn operators() {
+        assert_eq!(messages("1 + true;"), ["Cannot apply `+` to int and bool"]);
+        assert_eq!(
+            messages("\"a\" - \"b\";"),
+            ["Cannot apply `-` to string and string"]
+        );
+        assert_eq!(
+            messages("1 < \"b\";"),
+            ["Cannot apply `<` to int and string"]
+        );
+        assert_eq!(messages("1 == \"1\";"), ["Cannot compare int and string"]);
+        assert_eq!(
+            messages("1 && true;"),
+            ["Cannot apply `&&` to int and bool"]
+        );
+        assert_eq!(messages("!1;"), ["Cannot apply `!` to int"]);
+        assert_eq!(messages("-true;"), ["Cannot apply `-` to bool"]);
+        assert_eq!(
+            messages("let s = \"a\"; s++;"),
+            ["Cannot apply `++` to string"]
+        );
+    }
+
+    #[test]
+    fn mismatch_points_at_the_operator_expression() {
+        assert_eq!(
+            errors("let x = 1;\nlet y = x + true;"),
+            vec![TypeError::new(
+                Error::new(
+                    ErrorType::TypeMismatch,
+                    "Cannot apply `+` to int and bool",
+                    "+"
+                ),
+                Position::new(2, 9, 17).with_bytes(19, 27),
+            )]
+        );
+    }
+
+    #[test]
+    fn functions() {
+        let source = "
+func scale(value: float, by: int): float { return value * by; }
+scale(1, 2.5);
+scale(1);
+scale(true, 1);
+let s: string = scale(1.0, 2);";
+
+        assert_eq!(
+            messages(source),
+            [
+                "Expected int, found float",
+                "Expected 2 arguments, found 1",
+                "Expected float, found bool",
+                "Expected string, found float",
+            ]
+        );
+    }
+
+    #[test]
+    fn return_types() {
+        assert_eq!(
+            messages("func f(): int { return \"a\"; }"),
+            ["Expected int, found string"]
+        );
+        assert_eq!(
+            messages("func f(): int { return; }"),
+            ["Expected int, found void"]
+        );
+        assert_eq!(
+            messages("func f() { return 1; }"),
+            ["Expected void, found int"]
+        );
+        assert_eq!(
+            messages("func f(x: int): int { if x > 0 { return 1; } }"),
+            ["Missing return in function `f` returning int"]
+        );
+        assert_eq!(
+            errors("func f(x: int): int { if x > 0 { return 1; } else { return 2; } }"),
+            vec![]
+        );
+        assert_eq!(messages("return 1;"), ["`return` outside of a function"]);
+    }
+
+    #[test]
+    fn functions_can_be_called_before_their_declaration() {
+        let source = "
+func even(n: int): bool { if n == 0 { return true; } return odd(n - 1); }
+func odd(n: int): bool { if n == 0 { return false; } return even(n - 1); }";
+
+        assert_eq!(errors(source), vec![]);
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(
+            errors("let x = y;"),
+            vec![TypeError::new(
+                Error::new(ErrorType::UndefinedVariable, "Undefined variable", "y"),
+                Position::new(1, 9, 10).with_bytes(8, 9),
+            )]
+        );
+        assert_eq!(
+            messages("{ let inner = 1; } inner;"),
+            ["Undefined variable"]
+        );
+        assert_eq!(
+            messages("const c = 1; c = 2;"),
+            ["Cannot assign to a constant"]
+        );
+        assert_eq!(messages("let n = 1; n();"), ["Cannot call int"]);
+    }
+
+    #[test]
+    fn indexing() {
+        let source = "
+let a = [1, 2];
+let first: int = a[0];
+a[1] = first + 1;
+let c: string = \"bs\"[0];";
+
+        assert_eq!(errors(source), vec![]);
+        assert_eq!(
+            messages("let a = [1]; a[\"x\"]; 1[0];"),
+            ["Expected an int index, found string", "Cannot index int"]
+        );
+        assert_eq!(
+            messages("const a = [1]; a[0] = 2;"),
+            ["Cannot assign to a constant"]
+        );
+    }
+
+    #[test]
+    fn unsupported_types() {
+        //nothing makes a map or a tuple at runtime yet, so no value could be stored in one
+        assert_eq!(
+            messages(
+                "let m: map<string, int> = 1;
+func f(pair: (int, string)) { }
+let g: func(): array<(int,)>? = f;"
+            ),
+            [
+                "Type map<string, int> is not supported yet",
+                "Expected map<string, int>, found int",
+                "Type (int, string) is not supported yet",
+                "Type (int,) is not supported yet",
+                "Expected func(): array<(int,)>?, found func((int, string)): void"
+            ]
+        );
+        assert_eq!(
+            errors("let m: map<string, int> = 1;")[0].error.error_type,
+            ErrorType::UnsupportedType
+        );
+    }
+
+    #[test]
+    fn interpolation() {
+        assert_eq!(
+            errors("let n = 1; let s: string = \"n is ${n + 0.5} ${n > 0}\";"),
+            vec![]
+        );
+        assert_eq!(
+            messages("func f() { } \"${f()} ${[1]} ${missing}\";"),
+            [
+                "Cannot interpolate void",
+                "Cannot interpolate array<int>",
+                "Undefined variable"
+            ]
+        );
+    }
+
+    #[test]
+    fn composite_types() {
+        let source = "
+func double(n: int): int { return n * 2; }
+let numbers: array<int> = [1, 2];
+let n: int = numbers[0];
+let floats: array<float> = [1, 2.5];
+let f: func(int): int = double;
+let maybe: int? = 3;
+for x in numbers { let y: int = x; }
+let nested = [[1], [2]];
+let inner: int = nested[0][0];";
+
+        assert_eq!(errors(source), vec![]);
+        assert_eq!(
+            messages(
+                "func double(n: int): int { return n; }
+let a: array<int> = [\"a\"];
+let s: string = [1][0];
+let g: func(int): bool = double;
+let m: string? = 1;
+let p: Point = 1;
+struct Point { x: int }"
+            ),
+            [
+                "Expected array<int>, found array<string>",
+                "Expected string, found int",
+                "Expected func(int): bool, found func(int): int",
+                "Expected string?, found int",
+                "Expected Point, found int",
+            ]
+        );
+    }
+
+    #[test]
+    fn structs() {
+        let source = "
+let p = Point { x: 1, y: 2.5 };
+let x: float = p.x + p.len();
+let s: Segment = Segment { from: p, to: p.scaled(2) };
+let y: float = s.to.y;
+s.from.x = 3;
+struct Point { x: float, y: float }
+struct Segment { from: Point, to: Point }
+impl Point {
+    func len(self): float { return self.x + self.y; }
+    func scaled(self, by: float): Point { return Point { x: self.x * by, y: self.y * by }; }
+}";
+
+        assert_eq!(errors(source), vec![]);
+        assert_eq!(
+            messages(
+                "struct P { x: int }
+let p = P { x: \"a\", z: 1 };
+let q = P { };
+let a: string = p.x;
+p.y;
+p.missing();
+1.x;
+let u: Unknown = Other { };
+impl Nope { }"
+            ),
+            [
+                "Expected int, found string",
+                "No field `z` on P",
+                "Missing field `x`",
+                "Expected string, found int",
+                "No field `y` on P",
+                "No field `missing` on P",
+                "No field `x` on int",
+                "Undefined type",
+                "Undefined type",
+                "Undefined type",
+            ]
+        );
+        assert_eq!(
+            messages("struct P { x: int }\nimpl P { func get(self): int { return self.y; } }\nP { x: 1 }.get(1);"),
+            ["No field `y` on P", "Expected 0 arguments, found 1"]
+        );
+    }
+
+    #[test]
+    fn enums_and_match() {
+        let source = "
+enum Shape { Circle(float), Rect(float, float), Empty }
+impl Shape {
+    func area(self): float {
+        return match self {
+            Shape::Circle(r) => 3 * r * r,
+            Shape::Rect(w, h) => w * h,
+            Shape::Empty => 0,
+        };
+    }
+}
+let s: Shape = Shape::Rect(1, 2);
+let a: float = s.area();
+let name: string = match s { Shape::Circle(_) => \"circle\", _ => \"other\" };
+let b = match a > 1.0 { true => 1, false => 2 };
+let c: int = b;
+match 3 { 1 => print(1), n => s = Shape::Empty }";
+
+        assert_eq!(errors(source), vec![]);
+        assert_eq!(
+            messages(
+                "enum E { A(int), B }
+E::A(\"x\");
+E::A();
+E::C;
+X::A;
+let e = E::B;
+match e { E::A(x) => x + \"s\", E::B => 1 };
+match e { E::A(x, y) => 1, 2 => 2, _ => 3 };
+let m: int = match e { E::A(x) => x, _ => \"b\" };"
+            ),
+            [
+                "Expected int, found string",
+                "Expected 1 argument, found 0",
+                "No variant `C` on E",
+                "Undefined type",
+                "Cannot apply `+` to int and string",
+                "Expected 1 field, found 2",
+                "Expected E, found int",
+                "Expected int, found string",
+            ]
+        );
+    }
+
+    #[test]
+    fn match_exhaustiveness() {
+        let check = |arms: &str| {
+            let source = format!(
+                "enum Shape {{ Circle(float), Rect(float, float), Empty }}\nenum Opt {{ Some(Shape), None }}\nlet s = Shape::Empty; let o = Opt::None; let b = true;\n{}",
+                arms
+            );
+            messages(&source)
+        };
+
+        assert_eq!(
+            check("match s { Shape::Circle(_) => 1, Shape::Rect(a, b) => 2, Shape::Empty => 3 };"),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            check("match s { Shape::Empty => 1, x => 2 };"),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            check(
+                "match o { Opt::Some(Shape::Circle(_)) => 1, Opt::Some(_) => 2, Opt::None => 3 };"
+            ),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            check("match b { true => 1, false => 2 };"),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            check("match s { Shape::Circle(_) => 1 };"),
+            ["Non-exhaustive match, missing `Shape::Rect(_, _)`, `Shape::Empty`"]
+        );
+        assert_eq!(
+            check("match o { Opt::Some(Shape::Circle(_)) => 1, Opt::None => 2 };"),
+            ["Non-exhaustive match, missing `Opt::Some(Shape::Rect(_, _))`"]
+        );
+        assert_eq!(
+            check("match s { Shape::Circle(r) if r > 1.0 => 1, Shape::Rect(_, _) => 2, Shape::Empty => 3 };"),
+            ["Non-exhaustive match, missing `Shape::Circle(_)`"]
+        );
+        assert_eq!(
+            check("match b { true => 1 };"),
+            ["Non-exhaustive match, missing `false`"]
+        );
+        assert_eq!(
+            check("match 1 { 1 => 1, 2 => 2 };"),
+            ["Non-exhaustive match, missing `_`"]
+        );
+
+        let errors = errors("enum E { A, B }\nlet e = E::A;\nlet x = match e {\n  E::A => 1,\n};");
+
+        assert_eq!(
+            errors,
+            vec![TypeError::new(
+                Error::new(
+                    ErrorType::NonExhaustiveMatch,
+                    "Non-exhaustive match, missing `E::B`",
+                    "match"
+                ),
+                Position::new(3, 9, 14).with_bytes(38, 62),
+            )]
+        );
+    }
+
+    #[test]
+    fn recursive_enum_exhaustiveness() {
+        let check = |arms: &str| {
+            messages(&format!(
+                "enum List {{ Cons(int, List), Nil }}\nlet l = List::Nil;\n{}",
+                arms
+            ))
+        };
+
+        assert_eq!(
+            check("match l { List::Cons(h, _) => h, List::Nil => 0 };"),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            check("match l { List::Cons(_, List::Nil) => 1, List::Cons(_, List::Cons(_, t)) => 2, List::Nil => 0 };"),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            check("match l { List::Cons(h, _) => h };"),
+            ["Non-exhaustive match, missing `List::Nil`"]
+        );
+        assert_eq!(
+            check("match l { List::Cons(_, List::Nil) => 1, List::Nil => 0 };"),
+            ["Non-exhaustive match, missing `List::Cons(_, List::Cons(_, _))`"]
+        );
+        assert_eq!(
+            check("match l { };"),
+            ["Non-exhaustive match, missing `List::Cons(_, _)`, `List::Nil`"]
+        );
+    }
+
+    #[test]
+    fn control_flow() {
+        assert_eq!(
+            messages("if 1 { } while \"x\" { } for (;1;) { }"),
+            [
+                "Expected a bool condition, found int",
+                "Expected a bool condition, found string",
+                "Expected a bool condition, found int",
+            ]
+        );
+        assert_eq!(messages("for x in 10 { }"), ["Cannot iterate over int"]);
+        assert_eq!(messages("break;"), ["`break` outside of a loop"]);
+        assert_eq!(
+            messages("while true { func f() { continue; } }"),
+            ["`continue` outside of a loop"]
+        );
+    }
+
+    #[test]
+    fn unannotated_bindings_are_inferred() {
+        let source = "
+let count = 10;
+let ratio = count / 4.0;
+let name = \"bs\" + \"-lang\";
+let done = ratio > 1 && !false;
+func half(n: int): float { return n / 2.0; }
+let h = half(count);
+for c in name { print(c); }";
+
+        let info = infer(&parse(source).unwrap());
+
+        assert_eq!(info.errors, vec![]);
+
+        let declared = info
+            .occurrences
+            .iter()
+            .filter(|occurrence| occurrence.declaration)
+            .map(|occurrence| (occurrence.name.as_str(), occurrence.ty.to_string()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            declared,
+            [
+                ("count", "int".to_string()),
+                ("ratio", "float".to_string()),
+                ("name", "string".to_string()),
+                ("done", "bool".to_string()),
+                ("half", "func(int): float".to_string()),
+                ("n", "int".to_string()),
+                ("h", "float".to_string()),
+                ("c", "string".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn type_of_identifier_occurrences() {
+        let info = infer(&parse("let a = 1.5;\nlet b = a * 2;\nb = a;").unwrap());
+
+        //the declaration of b
+        assert_eq!(info.type_at(2, 5), Some(&Ty::Float));
+        //the use of a inside b's initializer
+        let occurrence = info.occurrence_at(2, 9).unwrap();
+
+        assert_eq!(occurrence.name, "a");
+        assert_eq!(occurrence.ty, Ty::Float);
+        assert!(!occurrence.declaration);
+
+        //both sides of the assignment
+        assert_eq!(info.occurrence_at(3, 1).unwrap().name, "b");
+        assert_eq!(info.occurrence_at(3, 5).unwrap().name, "a");
+
+        //the `*` is not an identifier
+        assert_eq!(info.type_at(2, 11), None);
+    }
+
+    #[test]
+    fn errors_do_not_cascade() {
+        //y has an unknown type after the first error, so using it is not reported again
+        assert_eq!(
+            messages("let y = missing; let z: int = y + 1;"),
+            ["Undefined variable"]
+        );
+
+        //neither are the holes the parser leaves where it found a syntax error
+        let (program, _) =
+            crate::Parser::new("let x = ;\nlet y: int = x + 1;\nlet = 2;").parse_recovering();
+
+        assert_eq!(check(&program), vec![]);
+    }
+}
+
+//a type as the checker sees it
+#[derive(Debug, Clone, PartialEq)]
+pub enum Ty {
+    Int,
+    Float,
+    String,
+    Bool,
+    //Unknown as the element type when it is not known, like for a plain `array`
+    Array(Box<Ty>),
+    Map(Box<Ty>, Box<Ty>),
+    Optional(Box<Ty>),
+    Tuple(Vec<Ty>),
+    Named(String),
+    Void,
+    Func(Vec<Ty>, Box<Ty>),
+    //the type of anything that already failed to check, it is compatible with everything
+    //so a single mistake is only reported once
+    Unknown,
+}
+
+impl Ty {
+    //the type named by an annotation
+    pub fn from_annotation(ty: &Type) -> Ty {
+        match ty {
+            Type::Int => Ty::Int,
+            Type::Float => Ty::Float,
+            Type::String => Ty::String,
+            Type::Bool => Ty::Bool,
+            Type::Array(element) => Ty::Array(Box::new(
+                element.as_deref().map_or(Ty::Unknown, Ty::from_annotation),
+            )),
+            Type::Map(key, value) => Ty::Map(
+                Box::new(Ty::from_annotation(key)),
+                Box::new(Ty::from_annotation(value)),
+            ),
+            Type::Optional(inner) => Ty::Optional(Box::new(Ty::from_annotation(inner))),
+            Type::Func(params, ret) => Ty::Func(
+                params.iter().map(Ty::from_annotation).collect(),
+                Box::new(ret.as_deref().map_or(Ty::Void, Ty::from_annotation)),
+            ),
+            Type::Tuple(elements) => Ty::Tuple(elements.iter().map(Ty::from_annotation).collect()),
+            Type::Named(name) => Ty::Named(name.clone()),
+        }
+    }
+
+    fn is_numeric(&self) -> bool {
+        matches!(self, Ty::Int | Ty::Float)
+    }
+
+    //whether a value of type found can be stored where expected is wanted, ints widen to floats
+    pub fn accepts(&self, found: &Ty) -> bool {
+        match (self, found) {
+            (Ty::Unknown, _) | (_, Ty::Unknown) => true,
+            (Ty::Float, Ty::Int) => true,
+            (Ty::Array(expected), Ty::Array(found)) => expected.accepts(found),
+            (Ty::Map(key, value), Ty::Map(found_key, found_value)) => {
+                key.accepts(found_key) && value.accepts(found_value)
+            }
+            (Ty::Optional(expected), Ty::Optional(found)) => expected.accepts(found),
+            (Ty::Optional(expected), found) => expected.accepts(found),
+            (Ty::Tuple(expected), Ty::Tuple(found)) => all_accept(expected, found),
+            //a function fits if it takes at least what is passed and returns what is wanted
+            (Ty::Func(params, ret), Ty::Func(found_params, found_ret)) => {
+                all_accept(found_params, params) && ret.accepts(found_ret)
+            }
+            (expected, found) => expected == found,
+        }
+    }
+
+    //the type of a list of values, like the elements of an array literal, mixed types
+    //only work out when one of them accepts all the others
+    fn common(types: impl IntoIterator<Item = Ty>) -> Ty {
+        let mut common: Option<Ty> = None;
+
+        for ty in types {
+            common = match common {
+                None => Some(ty),
+                Some(current) if current.accepts(&ty) => Some(current),
+                Some(current) if ty.accepts(&current) => Some(ty),
+                Some(_) => return Ty::Unknown,
+            };
+        }
+
+        common.unwrap_or(Ty::Unknown)
+    }
+}
+
+impl fmt::Display for Ty {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Ty::Int => write!(f, "int"),
+            Ty::Float => write!(f, "float"),
+            Ty::String => write!(f, "string"),
+            Ty::Bool => write!(f, "bool"),
+            Ty::Array(element) if **element == Ty::Unknown => write!(f, "array"),
+            Ty::Array(element) => write!(f, "array<{}>", element),
+            Ty::Map(key, value) => write!(f, "map<{}, {}>", key, value),
+            Ty::Optional(inner) if matches!(**inner, Ty::Func(..)) => write!(f, "({})?", inner),
+            Ty::Optional(inner) => write!(f, "{}?", inner),
+            Ty::Tuple(elements) if elements.len() == 1 => write!(f, "({},)", elements[0]),
+            Ty::Tuple(elements) => write!(f, "({})", join(elements)),
+            Ty::Named(name) => write!(f, "{}", name),
+            Ty::Void => write!(f, "void"),
+            Ty::Func(params, ret) => write!(f, "func({}): {}", join(params), ret),
+            Ty::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+fn join(types: &[Ty]) -> String {
+    types
+        .iter()
+        .map(Ty::to_string)
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+fn all_accept(expected: &[Ty], found: &[Ty]) -> bool {
+    expected.len() == found.len()
+        && expected
+            .iter()
+            .zip(found)
+            .all(|(expected, found)| expected.accepts(found))
+}
+
+//a type error and where it happened
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeError {
+    pub error: Error,
+    pub position: Position,
+}
+
+impl TypeError {
+    pub fn new(error: Error, position: Position) -> Self {
+        TypeError { error, position }
+    }
+}
+
+//check a whole program and return every type error in it
+pub fn check(program: &Program) -> Vec<TypeError> {
+    infer(program).errors
+}
+
+//check a whole program and keep the type of every identifier along with the errors
+pub fn infer(program: &Program) -> TypeInfo {
+    let mut checker = TypeChecker::new();
+    checker.check_statements(&program.statements);
+
+    TypeInfo {
+        errors: checker.errors,
+        occurrences: checker.occurrences,
+    }
+}
+
+//one place an identifier shows up in the source
+#[derive(Debug, Clone, PartialEq)]
+pub struct Occurrence {
+    pub name: String,
+    pub position: Position,
+    pub ty: Ty,
+    //true where the name is declared, false where it is used
+    pub declaration: bool,
+}
+
+//what the checker learned about a program
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeInfo {
+    pub errors: Vec<TypeError>,
+    //in source order of checking, so declarations come before their uses
+    pub occurrences: Vec<Occurrence>,
+}
+
+impl TypeInfo {
+    //the identifier covering a line and column, for tooling like hover
+    pub fn occurrence_at(&self, line: usize, column: usize) -> Option<&Occurrence> {
+        self.occurrences.iter().find(|occurrence| {
+            occurrence.position.line == line
+                && occurrence.position.start_column <= column
+                && column < occurrence.position.end_column
+        })
+    }
+
+    pub fn type_at(&self, line: usize, column: usize) -> Option<&Ty> {
+        self.occurrence_at(line, column)
+            .map(|occurrence| &occurrence.ty)
+    }
+}
+
+struct Variable {
+    ty: Ty,
+    mutable: bool,
+}
+
+//what the statements being checked are nested in
+#[derive(Clone)]
+struct Context {
+    //the return type of the enclosing function, None at the top level
+    return_ty: Option<Ty>,
+    in_loop: bool,
+}
+
+pub struct TypeChecker {
+    scopes: Vec<HashMap<String, Variable>>,
+    context: Context,
+    errors: Vec<TypeError>,
+    occurrences: Vec<Occurrence>,
+    //field types of every struct, payload types of every enum variant and the signatures
+    //of their methods without self
+    structs: HashMap<String, Vec<(String, Ty)>>,
+    enums: HashMap<String, Vec<(String, Vec<Ty>)>>,
+    methods: HashMap<String, HashMap<String, Ty>>,
+}
+
+impl Default for TypeChecker {
+    fn default() -> Self {
+        TypeChecker::new()
+    }
+}
+
+impl TypeChecker {
+    pub fn new() -> Self {
+        TypeChecker {
+            scopes: vec![HashMap::new()],
+            context: Context {
+                return_ty: None,
+                in_loop: false,
+            },
+            errors: Vec::new(),
+            occurrences: Vec::new(),
+            structs: HashMap::new(),
+            enums: HashMap::new(),
+            methods: HashMap::new(),
+        }
+    }
+
+    fn error(&mut self, error_type: ErrorType, message: &str, error: &str, position: &Position) {
+        self.errors.push(TypeError::new(
+            Error::new(error_type, message, error),
+            position.clone(),
+        ));
+    }
+
+    fn mismatch(&mut self, expected: &Ty, found: &Ty, name: &str, position: &Position) {
+        self.error(
+            ErrorType::TypeMismatch,
+            &format!("Expected {}, found {}", expected, found),
+            name,
+            position,
+        );
+    }
+
+    fn define(&mut self, name: &str, ty: Ty, mutable: bool) {
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .insert(name.to_string(), Variable { ty, mutable });
+    }
+
+    //define a name and remember the type it was given at its declaration
+    fn declare(&mut self, name: &str, position: &Position, ty: Ty, mutable: bool) {
+        self.record(name, position, ty.clone(), true);
+        self.define(name, ty, mutable);
+    }
+
+    fn record(&mut self, name: &str, position: &Position, ty: Ty, declaration: bool) {
+        self.occurrences.push(Occurrence {
+            name: name.to_string(),
+            position: position.clone(),
+            ty,
+            declaration,
+        });
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Variable> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    fn check_statements(&mut self, statements: &[Stmt]) {
+        //types, functions and methods may be used before the statement declaring them,
+        //so their signatures are known up front
+        for stmt in statements {
+            match stmt {
+                Stmt::Struct { name, fields, .. } => {
+                    let fields = fields
+                        .iter()
+                        .map(|field| (field.name.clone(), Ty::from_annotation(&field.ty)))
+                        .collect();
+
+                    self.structs.insert(name.clone(), fields);
+                }
+                Stmt::Enum { name, variants, .. } => {
+                    let variants = variants
+                        .iter()
+                        .map(|variant| {
+                            let fields = variant.fields.iter().map(Ty::from_annotation).collect();
+                            (variant.name.clone(), fields)
+                        })
+                        .collect();
+
+                    self.enums.insert(name.clone(), variants);
+                }
+                _ => {}
+            }
+        }
+
+        for stmt in statements {
+            match stmt {
+                Stmt::Func {
+                    name,
+                    params,
+                    return_ty,
+                    ..
+                } => self.define(name, function_type(params, return_ty.as_ref()), false),
+                Stmt::Impl { name, methods, .. } => {
+                    for method in methods {
+                        if let Stmt::Func {
+                            name: method,
+                            params,
+                            return_ty,
+                            ..
+                        } = method
+                        {
+                            let ty = function_type(&params[1..], return_ty.as_ref());
+
+                            self.methods
+                                .entry(name.clone())
+                                .or_default()
+                                .insert(method.clone(), ty);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        for stmt in statements {
+            self.check_statement(stmt);
+        }
+    }
+
+    fn check_block(&mut self, block: &Block) {
+        self.scopes.push(HashMap::new());
+        self.check_statements(&block.statements);
+        self.scopes.pop();
+    }
+
+    //check a loop body with break and continue allowed
+    fn check_loop_body(&mut self, body: &Block) {
+        let outer = self.context.in_loop;
+        self.context.in_loop = true;
+        self.check_block(body);
+        self.context.in_loop = outer;
+    }
+
+    fn check_statement(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Let {
+                name,
+                name_position,
+                ty,
+                init,
+                position,
+                ..
+            } => self.check_declaration(name, name_position, ty.as_ref(), init, true, position),
+            Stmt::Const {
+                name,
+                name_position,
+                ty,
+                init,
+                position,
+                ..
+            } => self.check_declaration(name, name_position, ty.as_ref(), init, false, position),
+            Stmt::Func {
+                name,
+                name_position,
+                params,
+                return_ty,
+                body,
+                position,
+                ..
+            } => {
+                //the signature was defined up front, only the occurrence is left to record
+                let ty = function_type(params, return_ty.as_ref());
+                self.record(name, name_position, ty, true);
+                self.check_function(name, params, return_ty.as_ref(), body, position);
+            }
+            Stmt::Struct { fields, .. } => {
+                for field in fields {
+                    self.resolve(&field.ty, &field.position);
+                }
+            }
+            Stmt::Enum { variants, .. } => {
+                for variant in variants {
+                    for ty in &variant.fields {
+                        self.resolve(ty, &variant.position);
+                    }
+                }
+            }
+            Stmt::Impl {
+                name,
+                name_position,
+                methods,
+                ..
+            } => {
+                if !self.is_type(name) {
+                    self.error(
+                        ErrorType::UndefinedType,
+                        "Undefined type",
+                        name,
+                        name_position,
+                    );
+                }
+
+                for method in methods {
+                    self.check_statement(method);
+                }
+            }
+            Stmt::Return { value, position } => {
+                let found = match value {
+                    Some(value) => self.check_expr(value),
+                    None => Ty::Void,
+                };
+
+                match self.context.return_ty.clone() {
+                    Some(expected) if !expected.accepts(&found) => {
+                        let position = value.as_ref().map_or(position, |value| &value.position);
+                        self.mismatch(&expected, &found, "return", position);
+                    }
+                    Some(_) => {}
+                    None => self.error(
+                        ErrorType::InvalidControlFlow,
+                        "`return` outside of a function",
+                        "return",
+                        position,
+                    ),
+                }
+            }
+            //the arms of a match used as a statement do not have to agree on a type
+            Stmt::Expr { expr, .. } => match &expr.kind {
+                ExprKind::Match { subject, arms } => {
+                    self.check_match(subject, arms, &expr.position, false);
+                }
+                _ => {
+                    self.check_expr(expr);
+                }
+            },
+            Stmt::Block(block) => self.check_block(block),
+            Stmt::If {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                self.check_condition(condition);
+                self.check_block(then_branch);
+
+                if let Some(else_branch) = else_branch {
+                    self.check_statement(else_branch);
+                }
+            }
+            Stmt::While {
+                condition, body, ..
+            } => {
+                self.check_condition(condition);
+                self.check_loop_body(body);
+            }
+            Stmt::For {
+                init,
+                condition,
+                update,
+                body,
+                ..
+            } => {
+                self.scopes.push(HashMap::new());
+
+                if let Some(init) = init {
+                    self.check_statement(init);
+                }
+                if let Some(condition) = condition {
+                    self.check_condition(condition);
+                }
+                if let Some(update) = update {
+                    self.check_expr(update);
+                }
+
+                self.check_loop_body(body);
+                self.scopes.pop();
+            }
+            Stmt::ForIn {
+                name,
+                name_position,
+                iterable,
+                body,
+                ..
+            } => {
+                let element = match self.check_expr(iterable) {
+                    Ty::String => Ty::String,
+                    Ty::Array(element) => *element,
+                    Ty::Unknown => Ty::Unknown,
+                    ty => {
+                        self.error(
+                            ErrorType::TypeMismatch,
+                            &format!("Cannot iterate over {}", ty),
+                            name,
+                            &iterable.position,
+                        );
+                        Ty::Unknown
+                    }
+                };
+
+                self.scopes.push(HashMap::new());
+                self.declare(name, name_position, element, true);
+                self.check_loop_body(body);
+                self.scopes.pop();
+            }
+            //the parser already reported what is wrong here
+            Stmt::Error { .. } => {}
+            Stmt::Break { position } | Stmt::Continue { position } => {
+                if !self.context.in_loop {
+                    let keyword = if matches!(stmt, Stmt::Break { .. }) {
+                        "break"
+                    } else {
+                        "continue"
+                    };
+
+                    self.error(
+                        ErrorType::InvalidControlFlow,
+                        &format!("`{}` outside of a loop", keyword),
+                        keyword,
+                        position,
+                    );
+                }
+            }
+        }
+    }
+
+    fn check_declaration(
+        &mut self,
+        name: &str,
+        name_position: &Position,
+        annotation: Option<&Type>,
+        init: &Expr,
+        mutable: bool,
+        position: &Position,
+    ) {
+        let found = self.check_expr(init);
+
+        let ty = match annotation.map(|annotation| self.resolve(annotation, position)) {
+            Some(expected) => {
+                if !expected.accepts(&found) {
+                    self.mismatch(&expected, &found, name, position);
+                }
+                expected
+            }
+            //without an annotation the binding takes the type of its initializer
+            None => found,
+        };
+
+        self.declare(name, name_position, ty, mutable);
+    }
+
+    fn check_function(
+        &mut self,
+        name: &str,
+        params: &[Param],
+        return_ty: Option<&Type>,
+        body: &Block,
+        position: &Position,
+    ) {
+        let return_ty = match return_ty {
+            Some(return_ty) => self.resolve(return_ty, position),
+            None => Ty::Void,
+        };
+
+        //a function body can not break out of a loop around the declaration
+        let outer = std::mem::replace(
+            &mut self.context,
+            Context {
+                return_ty: Some(return_ty.clone()),
+                in_loop: false,
+            },
+        );
+
+        self.scopes.push(HashMap::new());
+
+        for param in params {
+            let ty = self.resolve(&param.ty, &param.position);
+            self.declare(&param.name, &param.position, ty, true);
+        }
+
+        self.check_block(body);
+        self.scopes.pop();
+        self.context = outer;
+
+        if return_ty != Ty::Void && !always_returns(&body.statements) {
+            self.error(
+                ErrorType::MissingReturn,
+                &format!(
+                    "Missing return in function `{}` returning {}",
+                    name, return_ty
+                ),
+                name,
+                position,
+            );
+        }
+    }
+
+    //the type an annotation stands for, reporting names that are not a declared struct and
+    //the types the interpreter has no values for
+    fn resolve(&mut self, annotation: &Type, position: &Position) -> Ty {
+        let mut undefined = Vec::new();
+        named_types(annotation, &mut undefined);
+
+        for name in undefined {
+            if !self.is_type(name) {
+                self.error(ErrorType::UndefinedType, "Undefined type", name, position);
+            }
+        }
+
+        let mut unsupported = Vec::new();
+        unsupported_types(annotation, &mut unsupported);
+
+        for ty in unsupported {
+            self.error(
+                ErrorType::UnsupportedType,
+                &format!("Type {} is not supported yet", ty),
+                &ty.to_string(),
+                position,
+            );
+        }
+
+        Ty::from_annotation(annotation)
+    }
+
+    fn is_type(&self, name: &str) -> bool {
+        self.structs.contains_key(name) || self.enums.contains_key(name)
+    }
+
+    fn check_condition(&mut self, condition: &Expr) {
+        let ty = self.check_expr(condition);
+
+        if !Ty::Bool.accepts(&ty) {
+            self.error(
+                ErrorType::TypeMismatch,
+                &format!("Expected a bool condition, found {}", ty),
+                "condition",
+                &condition.position,
+            );
+        }
+    }
+
+    fn check_expr(&mut self, expr: &Expr) -> Ty {
+        match &expr.kind {
+            ExprKind::Int(_) => Ty::Int,
+            ExprKind::Float(_) => Ty::Float,
+            ExprKind::String(_) => Ty::String,
+            ExprKind::Interpolation(parts) => {
+                for part in parts {
+                    let ty = self.check_expr(part);
+
+                    if !matches!(
+                        ty,
+                        Ty::String | Ty::Int | Ty::Float | Ty::Bool | Ty::Unknown
+                    ) {
+                        self.error(
+                            ErrorType::TypeMismatch,
+                            &format!("Cannot interpolate {}", ty),
+                            "${",
+                            &part.position,
+                        );
+                    }
+                }
+                Ty::String
+            }
+            ExprKind::Bool(_) => Ty::Bool,
+            ExprKind::Array(elements) => {
+                let types = elements
+                    .iter()
+                    .map(|element| self.check_expr(element))
+                    .collect::<Vec<Ty>>();
+
+                Ty::Array(Box::new(Ty::common(types)))
+            }
+            ExprKind::Identifier(name) => match self.lookup(name) {
+                Some(variable) => {
+                    let ty = variable.ty.clone();
+                    self.record(name, &expr.position, ty.clone(), false);
+                    ty
+                }
+                //builtins are only checked where they are called
+                None if is_builtin(name) => Ty::Unknown,
+                None => {
+                    self.error(
+                        ErrorType::UndefinedVariable,
+                        "Undefined variable",
+                        name,
+                        &expr.position,
+                    );
+                    Ty::Unknown
+                }
+            },
+            ExprKind::Unary { op, operand } => {
+                let ty = self.check_expr(operand);
+
+                match op {
+                    UnaryOp::Neg if ty.is_numeric() => ty,
+                    UnaryOp::Not if ty == Ty::Bool => Ty::Bool,
+                    UnaryOp::Increment | UnaryOp::Decrement if ty.is_numeric() => {
+                        self.check_assignable(operand);
+                        ty
+                    }
+                    _ if ty == Ty::Unknown => Ty::Unknown,
+                    _ => {
+                        self.unary_mismatch(op.as_str(), &ty, &expr.position);
+                        Ty::Unknown
+                    }
+                }
+            }
+            ExprKind::Postfix { op, operand } => {
+                let ty = self.check_expr(operand);
+
+                if ty.is_numeric() {
+                    self.check_assignable(operand);
+                    ty
+                } else {
+                    if ty != Ty::Unknown {
+                        self.unary_mismatch(op.as_str(), &ty, &expr.position);
+                    }
+                    Ty::Unknown
+                }
+            }
+            ExprKind::Binary { op, left, right } => {
+                let left = self.check_expr(left);
+                let right = self.check_expr(right);
+
+                self.check_binary(*op, &left, &right, &expr.position)
+            }
+            ExprKind::Call { callee, args } => self.check_call(callee, args, &expr.position),
+            ExprKind::Index { target, index } => {
+                let target = self.check_expr(target);
+                let index = self.check_expr(index);
+
+                if !Ty::Int.accepts(&index) {
+                    self.error(
+                        ErrorType::TypeMismatch,
+                        &format!("Expected an int index, found {}", index),
+                        "[",
+                        &expr.position,
+                    );
+                }
+
+                match target {
+                    Ty::Array(element) => *element,
+                    Ty::Unknown => Ty::Unknown,
+                    Ty::String => Ty::String,
+                    target => {
+                        self.error(
+                            ErrorType::TypeMismatch,
+                            &format!("Cannot index {}", target),
+                            "[",
+                            &expr.position,
+                        );
+                        Ty::Unknown
+                    }
+                }
+            }
+            ExprKind::StructLiteral { name, fields } => {
+                self.check_struct_literal(name, fields, &expr.position)
+            }
+            ExprKind::Field { target, name } => {
+                let target = self.check_expr(target);
+                self.field_type(&target, name, &expr.position)
+            }
+            ExprKind::Variant {
+                enum_name,
+                variant,
+                args,
+            } => self.check_variant(enum_name, variant, args, &expr.position),
+            ExprKind::Match { subject, arms } => {
+                self.check_match(subject, arms, &expr.position, true)
+            }
+            ExprKind::Error => Ty::Unknown,
+            ExprKind::Assign { target, value } => {
+                let value_ty = self.check_expr(value);
+                let target_ty = self.check_expr(target);
+
+                if self.check_assignable(target) && !target_ty.accepts(&value_ty) {
+                    self.mismatch(&target_ty, &value_ty, "=", &value.position);
+                }
+
+                target_ty
+            }
+        }
+    }
+
+    fn check_struct_literal(&mut self, name: &str, inits: &[FieldInit], position: &Position) -> Ty {
+        let fields = self.structs.get(name).cloned();
+
+        if fields.is_none() {
+            self.error(ErrorType::UndefinedType, "Undefined type", name, position);
+        }
+
+        for init in inits {
+            let found = self.check_expr(&init.value);
+
+            let Some(fields) = &fields else {
+                continue;
+            };
+
+            match fields.iter().find(|(field, _)| *field == init.name) {
+                Some((_, expected)) if !expected.accepts(&found) => {
+                    self.mismatch(expected, &found, &init.name, &init.value.position)
+                }
+                Some(_) => {}
+                None => self.no_field(name, &init.name, &init.position),
+            }
+        }
+
+        let Some(fields) = fields else {
+            return Ty::Unknown;
+        };
+
+        for (field, _) in &fields {
+            if !inits.iter().any(|init| init.name == *field) {
+                self.error(
+                    ErrorType::MissingField,
+                    &format!("Missing field `{}`", field),
+                    name,
+                    position,
+                );
+            }
+        }
+
+        Ty::Named(name.to_string())
+    }
+
+    fn field_type(&mut self, target: &Ty, name: &str, position: &Position) -> Ty {
+        let field = match target {
+            Ty::Unknown => return Ty::Unknown,
+            Ty::Named(target) => self.structs.get(target).and_then(|fields| {
+                fields
+                    .iter()
+                    .find(|(field, _)| field == name)
+                    .map(|(_, ty)| ty.clone())
+            }),
+            _ => None,
+        };
+
+        field.unwrap_or_else(|| {
+            self.no_field(&target.to_string(), name, position);
+            Ty::Unknown
+        })
+    }
+
+    //the signature of target.name(), a field holding a function or a method
+    fn check_method(&mut self, target: &Expr, name: &str, position: &Position) -> Ty {
+        let target = self.check_expr(target);
+
+        if let Ty::Named(target) = &target {
+            let is_field = self
+                .structs
+                .get(target)
+                .is_some_and(|fields| fields.iter().any(|(field, _)| field == name));
+
+            let method = self
+                .methods
+                .get(target)
+                .and_then(|methods| methods.get(name));
+
+            if let (false, Some(method)) = (is_field, method) {
+                return method.clone();
+            }
+        }
+
+        self.field_type(&target, name, position)
+    }
+
+    fn check_variant(
+        &mut self,
+        enum_name: &str,
+        variant: &str,
+        args: &[Expr],
+        position: &Position,
+    ) -> Ty {
+        let arg_types = args
+            .iter()
+            .map(|arg| self.check_expr(arg))
+            .collect::<Vec<Ty>>();
+
+        let Some(fields) = self.variant_fields(enum_name, variant, position) else {
+            return self.enum_type(enum_name);
+        };
+
+        if fields.len() != args.len() {
+            self.argument_count(variant, fields.len(), args.len(), position);
+        } else {
+            for ((field, arg_ty), arg) in fields.iter().zip(&arg_types).zip(args) {
+                if !field.accepts(arg_ty) {
+                    self.mismatch(field, arg_ty, variant, &arg.position);
+                }
+            }
+        }
+
+        self.enum_type(enum_name)
+    }
+
+    //the enum as a type, unknown when it was never declared so the mistake is reported once
+    fn enum_type(&self, enum_name: &str) -> Ty {
+        if self.enums.contains_key(enum_name) {
+            Ty::Named(enum_name.to_string())
+        } else {
+            Ty::Unknown
+        }
+    }
+
+    //the payload types of a variant, None after reporting an unknown enum or variant
+    fn variant_fields(
+        &mut self,
+        enum_name: &str,
+        variant: &str,
+        position: &Position,
+    ) -> Option<Vec<Ty>> {
+        let Some(variants) = self.enums.get(enum_name) else {
+            self.error(
+                ErrorType::UndefinedType,
+                "Undefined type",
+                enum_name,
+                position,
+            );
+            return None;
+        };
+
+        let fields = variants
+            .iter()
+            .find(|(name, _)| name == variant)
+            .map(|(_, fields)| fields.clone());
+
+        if fields.is_none() {
+            self.error(
+                ErrorType::UndefinedVariant,
+                &format!("No variant `{}` on {}", variant, enum_name),
+                variant,
+                position,
+            );
+        }
+
+        fields
+    }
+
+    //the type of a match is the type all arms agree on, unless it is only used as a statement
+    fn check_match(
+        &mut self,
+        subject: &Expr,
+        arms: &[MatchArm],
+        position: &Position,
+        unify: bool,
+    ) -> Ty {
+        let subject_ty = self.check_expr(subject);
+        let mut result: Option<Ty> = None;
+
+        for arm in arms {
+            self.scopes.push(HashMap::new());
+            self.check_pattern(&arm.pattern, &subject_ty);
+
+            if let Some(guard) = &arm.guard {
+                self.check_condition(guard);
+            }
+
+            let ty = self.check_expr(&arm.body);
+            self.scopes.pop();
+
+            result = match result {
+                None => Some(ty),
+                Some(current) if !unify || current.accepts(&ty) => Some(current),
+                Some(current) if ty.accepts(&current) => Some(ty),
+                Some(current) => {
+                    self.mismatch(&current, &ty, "match", &arm.body.position);
+                    Some(current)
+                }
+            };
+        }
+
+        self.check_exhaustive(arms, &subject_ty, position);
+
+        match result {
+            Some(ty) if unify => ty,
+            _ => Ty::Void,
+        }
+    }
+
+    fn check_pattern(&mut self, pattern: &Pattern, ty: &Ty) {
+        match &pattern.kind {
+            PatternKind::Wildcard => {}
+            PatternKind::Binding(name) => self.declare(name, &pattern.position, ty.clone(), true),
+            PatternKind::Literal(literal) => {
+                let found = self.check_expr(literal);
+
+                if !ty.accepts(&found) && !found.accepts(ty) {
+                    self.mismatch(ty, &found, "match", &pattern.position);
+                }
+            }
+            PatternKind::Variant {
+                enum_name,
+                variant,
+                fields,
+            } => {
+                let found = self.enum_type(enum_name);
+                let declared = self.variant_fields(enum_name, variant, &pattern.position);
+
+                if !ty.accepts(&found) {
+                    self.mismatch(ty, &found, variant, &pattern.position);
+                }
+
+                match declared {
+                    Some(declared) if declared.len() == fields.len() => {
+                        for (field, ty) in fields.iter().zip(&declared) {
+                            self.check_pattern(field, ty);
+                        }
+                    }
+                    declared => {
+                        if let Some(declared) = declared {
+                            let plural = if declared.len() == 1 { "" } else { "s" };
+
+                            self.error(
+                                ErrorType::WrongArgumentCount,
+                                &format!(
+                                    "Expected {} field{}, found {}",
+                                    declared.len(),
+                                    plural,
+                                    fields.len()
+                                ),
+                                variant,
+                                &pattern.position,
+                            );
+                        }
+
+                        for field in fields {
+                            self.check_pattern(field, &Ty::Unknown);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    //report the values no arm matches, arms with a guard may not match so they are left out
+    fn check_exhaustive(&mut self, arms: &[MatchArm], ty: &Ty, position: &Position) {
+        if *ty == Ty::Unknown {
+            return;
+        }
+
+        let rows = arms
+            .iter()
+            .filter(|arm| arm.guard.is_none())
+            .map(|arm| vec![Some(&arm.pattern)])
+            .collect::<Vec<Row>>();
+
+        let missing = self
+            .missing(&rows, std::slice::from_ref(ty), true)
+            .into_iter()
+            .map(|witness| format!("`{}`", witness.join(", ")))
+            .collect::<Vec<String>>();
+
+        if !missing.is_empty() {
+            self.error(
+                ErrorType::NonExhaustiveMatch,
+                &format!("Non-exhaustive match, missing {}", missing.join(", ")),
+                "match",
+                position,
+            );
+        }
+    }
+
+    //values of the types in tys that no row matches, written as patterns, this is the usual
+    //split on the constructors of the first column; only the top level lists every missing
+    //constructor, below that the first example is enough
+    fn missing(&self, rows: &[Row], tys: &[Ty], all: bool) -> Vec<Vec<String>> {
+        let Some((ty, rest)) = tys.split_first() else {
+            return if rows.is_empty() {
+                vec![vec![]]
+            } else {
+                vec![]
+            };
+        };
+
+        //a column is only split into constructors when a row looks inside it, so a recursive
+        //enum behind wildcards is not unfolded forever; with no arms at all the top level
+        //still lists every constructor
+        let split = rows.iter().any(|row| !catches_all(row[0])) || all && rows.is_empty();
+
+        let Some(constructors) = self.constructors(ty).filter(|_| split) else {
+            //ints, strings and the like have too many values to list, only a catch-all covers
+            //them, the same goes for a column no row looks inside
+            let rows = rows
+                .iter()
+                .filter(|row| catches_all(row[0]))
+                .map(|row| row[1..].to_vec())
+                .collect::<Vec<Row>>();
+
+            return self
+                .missing(&rows, rest, false)
+                .into_iter()
+                .take(1)
+                .map(|witness| std::iter::once("_".to_string()).chain(witness).collect())
+                .collect();
+        };
+
+        let mut missing = Vec::new();
+
+        for (name, fields) in constructors {
+            let arity = fields.len();
+
+            let rows = rows
+                .iter()
+                .filter_map(|row| {
+                    let head = match row[0].map(|pattern| &pattern.kind) {
+                        _ if catches_all(row[0]) => vec![None; arity],
+                        Some(PatternKind::Variant {
+                            enum_name,
+                            variant,
+                            fields,
+                        }) if format!("{}::{}", enum_name, variant) == name
+                            && fields.len() == arity =>
+                        {
+                            fields.iter().map(Some).collect()
+                        }
+                        Some(PatternKind::Literal(Expr {
+                            kind: ExprKind::Bool(value),
+                            ..
+                        })) if value.to_string() == name => vec![],
+                        _ => return None,
+                    };
+
+                    Some(head.into_iter().chain(row[1..].iter().copied()).collect())
+                })
+                .collect::<Vec<Row>>();
+
+            let tys = fields
+                .into_iter()
+                .chain(rest.iter().cloned())
+                .collect::<Vec<Ty>>();
+
+            if let Some(witness) = self.missing(&rows, &tys, false).into_iter().next() {
+                let (fields, rest) = witness.split_at(arity);
+
+                let head = if arity == 0 {
+                    name
+                } else {
+                    format!("{}({})", name, fields.join(", "))
+                };
+
+                missing.push(std::iter::once(head).chain(rest.iter().cloned()).collect());
+
+                if !all {
+                    break;
+                }
+            }
+        }
+
+        missing
+    }
+
+    //every shape a value of the type can have with the types of its payload, None when
+    //there are too many to list
+    fn constructors(&self, ty: &Ty) -> Option<Vec<(String, Vec<Ty>)>> {
+        match ty {
+            Ty::Bool => Some(vec![
+                ("true".to_string(), vec![]),
+                ("false".to_string(), vec![]),
+            ]),
+            Ty::Named(name) => self.enums.get(name).map(|variants| {
+                variants
+                    .iter()
+                    .map(|(variant, fields)| (format!("{}::{}", name, variant), fields.clone()))
+                    .collect()
+            }),
+            _ => None,
+        }
+    }
+
+    fn no_field(&mut self, type_name: &str, name: &str, position: &Position) {
+        self.error(
+            ErrorType::UndefinedField,
+            &format!("No field `{}` on {}", name, type_name),
+            name,
+            position,
+        );
+    }
+
+    fn unary_mismatch(&mut self, op: &str, ty: &Ty, position: &Position) {
+        self.error(
+            ErrorType::TypeMismatch,
+            &format!("Cannot apply `{}` to {}", op, ty),
+            op,
+            position,
+        );
+    }
+
+    //report assignments to constants, returns false if the target can not be assigned
+    fn check_assignable(&mut self, target: &Expr) -> bool {
+        //a[i] = v writes to a, so a has to be assignable
+        if let ExprKind::Index { target, .. } = &target.kind {
+            return self.check_assignable(target);
+        }
+
+        //structs are shared, so their fields can be changed through any binding
+        if let ExprKind::Field { .. } = &target.kind {
+            return true;
+        }
+
+        let ExprKind::Identifier(name) = &target.kind else {
+            return false;
+        };
+
+        match self.lookup(name) {
+            Some(variable) if !variable.mutable => {
+                self.error(
+                    ErrorType::InvalidAssignment,
+                    "Cannot assign to a constant",
+                    name,
+                    &target.position,
+                );
+                false
+            }
+            Some(_) => true,
+            None => false,
+        }
+    }
+
+    fn check_binary(&mut self, op: BinaryOp, left: &Ty, right: &Ty, position: &Position) -> Ty {
+        if *left == Ty::Unknown || *right == Ty::Unknown {
+            return match op {
+                BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem => {
+                    Ty::Unknown
+                }
+                _ => Ty::Bool,
+            };
+        }
+
+        let result = match op {
+            BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem => {
+                match (left, right) {
+                    (Ty::Int, Ty::Int) => Some(Ty::Int),
+                    (Ty::String, Ty::String) if op == BinaryOp::Add => Some(Ty::String),
+                    (left, right) if left.is_numeric() && right.is_numeric() => Some(Ty::Float),
+                    _ => None,
+                }
+            }
+            BinaryOp::Less | BinaryOp::Greater | BinaryOp::LessEq | BinaryOp::GreaterEq => {
+                let comparable = (left.is_numeric() && right.is_numeric())
+                    || (*left == Ty::String && *right == Ty::String);
+                comparable.then_some(Ty::Bool)
+            }
+            BinaryOp::And | BinaryOp::Or => {
+                (*left == Ty::Bool && *right == Ty::Bool).then_some(Ty::Bool)
+            }
+            BinaryOp::Eq | BinaryOp::NotEq => {
+                if left.accepts(right) || right.accepts(left) {
+                    Some(Ty::Bool)
+                } else {
+                    self.error(
+                        ErrorType::TypeMismatch,
+                        &format!("Cannot compare {} and {}", left, right),
+                        op.as_str(),
+                        position,
+                    );
+                    return Ty::Bool;
+                }
+            }
+        };
+
+        result.unwrap_or_else(|| {
+            self.error(
+                ErrorType::TypeMismatch,
+                &format!("Cannot apply `{}` to {} and {}", op.as_str(), left, right),
+                op.as_str(),
+                position,
+            );
+            Ty::Unknown
+        })
+    }
+
+    fn check_call(&mut self, callee: &Expr, args: &[Expr], position: &Position) -> Ty {
+        let arg_types = args
+            .iter()
+            .map(|arg| self.check_expr(arg))
+            .collect::<Vec<Ty>>();
+
+        //builtins are used when no variable shadows their name
+        if let ExprKind::Identifier(name) = &callee.kind {
+            if self.lookup(name).is_none() && is_builtin(name) {
+                return self.check_builtin_call(name, &arg_types, args, position);
+            }
+        }
+
+        let name = match &callee.kind {
+            ExprKind::Identifier(name) | ExprKind::Field { name, .. } => name.clone(),
+            _ => "call".to_string(),
+        };
+
+        let callee = match &callee.kind {
+            ExprKind::Field { target, name } => self.check_method(target, name, &callee.position),
+            _ => self.check_expr(callee),
+        };
+
+        match callee {
+            Ty::Func(params, ret) => {
+                if params.len() != args.len() {
+                    self.argument_count(&name, params.len(), args.len(), position);
+                    return *ret;
+                }
+
+                for ((param, arg_ty), arg) in params.iter().zip(&arg_types).zip(args) {
+                    if !param.accepts(arg_ty) {
+                        self.mismatch(param, arg_ty, &name, &arg.position);
+                    }
+                }
+
+                *ret
+            }
+            Ty::Unknown => Ty::Unknown,
+            ty => {
+                self.error(
+                    ErrorType::TypeMismatch,
+                    &format!("Cannot call {}", ty),
+                    &name,
+                    position,
+                );
+                Ty::Unknown
+            }
+        }
+    }
+
+    fn check_builtin_call(
+        &mut self,
+        name: &str,
+        arg_types: &[Ty],
+        args: &[Expr],
+        position: &Position,
+    ) -> Ty {
+        match name {
+            "len" => {
+                match arg_types {
+                    [Ty::Array(_) | Ty::String | Ty::Unknown] => {}
+                    [ty] => self.error(
+                        ErrorType::TypeMismatch,
+                        &format!("Cannot take the length of {}", ty),
+                        name,
+                        &args[0].position,
+                    ),
+                    _ => self.argument_count(name, 1, args.len(), position),
+                }
+                Ty::Int
+            }
+            //print takes any number of values of any type
+            _ => Ty::Void,
+        }
+    }
+
+    fn argument_count(&mut self, name: &str, expected: usize, found: usize, position: &Position) {
+        let plural = if expected == 1 { "" } else { "s" };
+
+        self.error(
+            ErrorType::WrongArgumentCount,
+            &format!("Expected {} argument{}, found {}", expected, plural, found),
+            name,
+            position,
+        );
+    }
+}
+
+//a row of patterns being checked for exhaustiveness, None stands for a `_` filled in
+//for the payload of a variant a catch-all arm already covers
+type Row<'a> = Vec<Option<&'a Pattern>>;
+
+fn catches_all(pattern: Option<&Pattern>) -> bool {
+    matches!(
+        pattern.map(|pattern| &pattern.kind),
+        None | Some(PatternKind::Wildcard | PatternKind::Binding(_))
+    )
+}
+
+fn is_builtin(name: &str) -> bool {
+    Builtin::from_name(name).is_some()
+}
+
+fn function_type(params: &[Param], return_ty: Option<&Type>) -> Ty {
+    Ty::Func(
+        params
+            .iter()
+            .map(|param| Ty::from_annotation(&param.ty))
+            .collect(),
+        Box::new(return_ty.map_or(Ty::Void, Ty::from_annotation)),
+    )
+}
+
+//the struct names used anywhere in an annotation
+fn named_types<'a>(ty: &'a Type, names: &mut Vec<&'a str>) {
+    match ty {
+        Type::Named(name) => names.push(name),
+        Type::Array(Some(element)) | Type::Optional(element) => named_types(element, names),
+        Type::Map(key, value) => {
+            named_types(key, names);
+            named_types(value, names);
+        }
+        Type::Func(params, ret) => {
+            params.iter().for_each(|param| named_types(param, names));
+
+            if let Some(ret) = ret {
+                named_types(ret, names);
+            }
+        }
+        Type::Tuple(elements) => elements
+            .iter()
+            .for_each(|element| named_types(element, names)),
+        Type::Int | Type::Float | Type::String | Type::Bool | Type::Array(None) => {}
+    }
+}
+
+//the map and tuple types in an annotation, they parse but nothing at runtime makes a map
+//or a tuple, so a value could never be stored in them
+fn unsupported_types<'a>(ty: &'a Type, found: &mut Vec<&'a Type>) {
+    match ty {
+        Type::Map(..) | Type::Tuple(_) => found.push(ty),
+        Type::Array(Some(element)) | Type::Optional(element) => unsupported_types(element, found),
+        Type::Func(params, ret) => {
+            params
+                .iter()
+                .for_each(|param| unsupported_types(param, found));
+
+            if let Some(ret) = ret {
+                unsupported_types(ret, found);
+            }
+        }
+        Type::Int
+        | Type::Float
+        | Type::String
+        | Type::Bool
+        | Type::Array(None)
+        | Type::Named(_) => {}
+    }
+}
+
+//whether running these statements always ends in a return
+fn always_returns(statements: &[Stmt]) -> bool {
+    statements.iter().any(|stmt| match stmt {
+        Stmt::Return { .. } => true,
+        Stmt::Block(block) => always_returns(&block.statements),
+        Stmt::If {
+            then_branch,
+            else_branch: Some(else_branch),
+            ..
+        } => {
+            always_returns(&then_branch.statements)
+                && always_returns(std::slice::from_ref(else_branch))
+        }
+        _ => false,
+    })
+}